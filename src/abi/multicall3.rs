@@ -0,0 +1,11 @@
+use ethers::contract::abigen;
+
+// only aggregate3, every sub-call carries its own allowFailure flag
+abigen!(
+    MULTICALL3,
+    r#"[
+        struct Call3 { address target; bool allowFailure; bytes callData; }
+        struct Call3Result { bool success; bytes returnData; }
+        function aggregate3(Call3[] calldata calls) external payable returns (Call3Result[] memory returnData)
+    ]"#
+);
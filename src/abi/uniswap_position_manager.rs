@@ -0,0 +1,12 @@
+use ethers::contract::abigen;
+
+// only the NonfungiblePositionManager entry points used to open a new pool
+abigen!(
+    UNISWAP_POSITION_MANAGER,
+    r#"[
+        struct MintParams { address token0; address token1; uint24 fee; int24 tickLower; int24 tickUpper; uint256 amount0Desired; uint256 amount1Desired; uint256 amount0Min; uint256 amount1Min; address recipient; uint256 deadline; }
+        function mint(MintParams calldata params) external payable returns (uint256 tokenId, uint128 liquidity, uint256 amount0, uint256 amount1)
+        function createAndInitializePoolIfNecessary(address token0, address token1, uint24 fee, uint160 sqrtPriceX96) external payable returns (address pool)
+        function multicall(bytes[] calldata data) external payable returns (bytes[] memory results)
+    ]"#
+);
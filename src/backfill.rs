@@ -0,0 +1,104 @@
+use crate::data::token_data::TokenRegistry;
+use crate::swap::launch_price::LaunchPriceBounds;
+use crate::uniswap_v3_events::{decode_poolcreated_event, set_signature_filter};
+use anyhow::Result;
+use ethers::providers::{Middleware, Provider, Ws};
+use ethers::types::{Filter, Log};
+use log::{debug, error, info, warn};
+use std::sync::Arc;
+
+pub const DEFAULT_PAGE_SIZE: u64 = 2_000;
+pub const MAX_PAGE_SIZE: u64 = 10_000;
+
+/// Replays PoolCreated events in `[from_block, to_block]` into the token registry,
+/// returns the number of pool created events found
+pub async fn backfill_pool_created_events(
+    registry: &TokenRegistry,
+    client: &Arc<Provider<Ws>>,
+    bounds: &LaunchPriceBounds,
+    from_block: u64,
+    to_block: u64,
+) -> Result<usize> {
+    info!(
+        "backfilling PoolCreated events from block {} to {}",
+        from_block, to_block
+    );
+    let filter = set_signature_filter()?;
+    let logs = get_logs_in_pages(client, &filter, from_block, to_block).await?;
+
+    let mut pools_found = 0;
+    for log in logs.iter() {
+        match decode_poolcreated_event(log) {
+            Ok(pool_created_event) => {
+                pools_found += 1;
+                if let Err(error) = registry
+                    .get_and_save_erc20_by_token_address(&pool_created_event, bounds, client)
+                    .await
+                {
+                    warn!("could not save backfilled token => {}", error);
+                }
+            }
+            Err(error) => error!("error extracting pool created event => {}", error),
+        }
+    }
+
+    info!("backfill done, found {} pools", pools_found);
+    Ok(pools_found)
+}
+
+/// Pages `get_logs` over `[from_block, to_block]`, halving the page whenever the RPC
+/// rejects a range as too large and growing it again after each successful page
+pub async fn get_logs_in_pages(
+    client: &Arc<Provider<Ws>>,
+    filter: &Filter,
+    from_block: u64,
+    to_block: u64,
+) -> Result<Vec<Log>> {
+    let mut logs = Vec::<Log>::new();
+    let mut page_size = DEFAULT_PAGE_SIZE;
+    let mut start = from_block;
+
+    while start <= to_block {
+        let end = page_end(start, page_size, to_block);
+        let page_filter = filter.clone().from_block(start).to_block(end);
+
+        match client.get_logs(&page_filter).await {
+            Ok(page_logs) => {
+                debug!("blocks {}..={} => {} logs", start, end, page_logs.len());
+                logs.extend(page_logs);
+                start = end + 1;
+                page_size = (page_size * 2).min(MAX_PAGE_SIZE);
+            }
+            Err(error) if page_size > 1 && is_range_too_large_error(&error.to_string()) => {
+                page_size /= 2;
+                debug!("range too large, reducing page size to {}", page_size);
+            }
+            Err(error) => return Err(error.into()),
+        }
+    }
+
+    Ok(logs)
+}
+
+pub fn page_end(start: u64, page_size: u64, to_block: u64) -> u64 {
+    start.saturating_add(page_size.max(1) - 1).min(to_block)
+}
+
+/// RPC providers word this differently, so match on the common phrases
+pub fn is_range_too_large_error(message: &str) -> bool {
+    let message = message.to_lowercase();
+
+    [
+        "block range",
+        "range too large",
+        "range is too large",
+        "too many blocks",
+        "query returned more than",
+        "response size exceeded",
+        "exceed maximum block range",
+        "log response size",
+        "limit exceeded",
+    ]
+    .iter()
+    .any(|phrase| message.contains(phrase))
+}
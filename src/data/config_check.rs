@@ -0,0 +1,171 @@
+use crate::data::contracts::{ContractAddresses, CONTRACT};
+use crate::strategy::Strategy;
+use crate::utils::secrets::load_signer;
+use anyhow::{anyhow, Result};
+use ethers::providers::{Http, Middleware, Provider, Ws};
+use ethers::signers::Signer;
+use ethers::types::{Address, Chain, U256};
+use std::fmt::Write;
+use url::Url;
+
+/// Fails unless `chain_id`, as reported by the `endpoint` RPC, is the id of `chain`
+pub fn ensure_chain_id(chain: Chain, endpoint: &str, chain_id: U256) -> Result<()> {
+    let expected = U256::from(u64::from(chain));
+    if chain_id != expected {
+        return Err(anyhow!(
+            "[{}] {} serves chain id {}, expected {}",
+            chain,
+            endpoint,
+            chain_id,
+            expected
+        ));
+    }
+
+    Ok(())
+}
+
+/// Asks the ws and http endpoints of `chain` for their chain id, returns every endpoint
+/// that can't be reached or serves another chain
+pub async fn rpc_problems(chain: Chain, contracts: &ContractAddresses) -> Vec<String> {
+    let ws_chain_id = match Provider::<Ws>::connect(contracts.ws_url.as_str()).await {
+        Ok(provider) => provider.get_chainid().await.map_err(anyhow::Error::from),
+        Err(error) => Err(error.into()),
+    };
+    let http_chain_id = match Provider::<Http>::try_from(contracts.http_url.as_str()) {
+        Ok(provider) => provider.get_chainid().await.map_err(anyhow::Error::from),
+        Err(error) => Err(error.into()),
+    };
+
+    let mut problems: Vec<String> = vec![];
+    for (endpoint, chain_id) in [("ws_url", ws_chain_id), ("http_url", http_chain_id)] {
+        let checked = chain_id
+            .map_err(|error| anyhow!("[{}] could not reach {} => {}", chain, endpoint, error))
+            .and_then(|chain_id| ensure_chain_id(chain, endpoint, chain_id));
+
+        if let Err(error) = checked {
+            problems.push(error.to_string());
+        }
+    }
+
+    problems
+}
+
+/// url without credentials, path or query, RPC providers put api keys in those
+pub fn redact_url(url: &Url) -> String {
+    let mut redacted = format!("{}://{}", url.scheme(), url.host_str().unwrap_or_default());
+    if let Some(port) = url.port() {
+        redacted.push_str(&format!(":{}", port));
+    }
+    if !url.username().is_empty() || url.password().is_some() {
+        redacted = redacted.replacen("://", "://<redacted>@", 1);
+    }
+    if !matches!(url.path(), "" | "/") {
+        redacted.push_str("/<redacted>");
+    }
+    if url.query().is_some() {
+        redacted.push_str("?<redacted>");
+    }
+
+    redacted
+}
+
+/// resolved contracts of `chain` as printed by `config check`, urls redacted
+pub fn describe_contracts(chain: Chain, contracts: &ContractAddresses) -> String {
+    let optional = |address: Option<Address>| match address {
+        Some(address) => format!("{:?}", address),
+        None => "not configured".to_string(),
+    };
+
+    let mut description = format!("[{}] chain id {}\n", chain, u64::from(chain));
+    let fields = [
+        ("ws_url", redact_url(&contracts.ws_url)),
+        ("http_url", redact_url(&contracts.http_url)),
+        ("weth", format!("{:?}", contracts.weth)),
+        ("link", format!("{:?}", contracts.link)),
+        (
+            "uniswap_factory",
+            format!("{:?}", contracts.uniswap_factory),
+        ),
+        (
+            "uniswap_swap_router",
+            format!("{:?}", contracts.uniswap_swap_router),
+        ),
+        ("uniswap_quoter", format!("{:?}", contracts.uniswap_quoter)),
+        (
+            "uniswap_position_manager",
+            optional(contracts.uniswap_position_manager),
+        ),
+        ("uniswap_v2_factory", optional(contracts.uniswap_v2_factory)),
+        ("uniswap_v2_router", optional(contracts.uniswap_v2_router)),
+    ];
+    for (name, value) in fields {
+        let _ = writeln!(description, "  {:<26}{}", name, value);
+    }
+
+    for quote_token in contracts.quote_tokens.iter() {
+        let _ = writeln!(
+            description,
+            "  quote token {:<14}{:?} ({} decimals, weth pool fee {})",
+            quote_token.symbol,
+            quote_token.address,
+            quote_token.decimals,
+            quote_token.weth_pool_fee
+        );
+    }
+
+    description
+}
+
+/// the strategy profile as printed by `config check`
+pub fn describe_strategy(strategy: &Strategy) -> String {
+    let mut description = format!("strategy profile {}\n", strategy.profile);
+    for (name, value) in strategy.fields() {
+        let _ = writeln!(description, "  {:<26}{}", name, value);
+    }
+
+    description
+}
+
+/// `config check`: prints the resolved configuration of `chains` and the strategy and
+/// checks that their RPCs serve the right chain, failing with every problem found
+pub async fn config_check(chains: &[Chain], strategy: &Strategy) -> Result<()> {
+    let mut problems: Vec<String> = vec![];
+
+    for &chain in chains {
+        let contracts = CONTRACT.get_address_for(chain)?;
+        println!("{}", describe_contracts(chain, contracts));
+        problems.extend(rpc_problems(chain, contracts).await);
+    }
+
+    let mut unavailable: Vec<(&Chain, &Vec<String>)> = CONTRACT.unavailable.iter().collect();
+    unavailable.sort_by_key(|(chain, _)| chain.to_string());
+    for (chain, unset_vars) in unavailable {
+        println!(
+            "[{}] unavailable, not set: {}\n",
+            chain,
+            unset_vars.join(", ")
+        );
+    }
+
+    println!("{}", describe_strategy(strategy));
+
+    // only the address is printed, the key never leaves the wallet
+    if let Some(&chain) = chains.first() {
+        match load_signer(chain) {
+            Ok(Some(wallet)) => println!("signer => {:?}", wallet.address()),
+            Ok(None) => println!("signer => no keystore configured"),
+            Err(error) => problems.push(format!("signer => {}", error)),
+        }
+    }
+
+    if !problems.is_empty() {
+        return Err(anyhow!(
+            "{} problem(s) in config:\n  {}",
+            problems.len(),
+            problems.join("\n  ")
+        ));
+    }
+
+    println!("config ok");
+    Ok(())
+}
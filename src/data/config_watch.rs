@@ -0,0 +1,99 @@
+use crate::data::contracts::{ContractAddressMap, CONTRACT, CONTRACTS_PATH};
+use crate::strategy::{load_strategy, SharedStrategy, STRATEGY_PATH};
+use anyhow::{anyhow, Result};
+use ethers::types::Chain;
+use log::{error, info};
+use std::fs;
+use std::time::{Duration, SystemTime};
+
+/// how often strategy.toml and contracts.toml are checked for edits
+pub const CONFIG_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Polls strategy.toml and contracts.toml for edits until the process exits. Strategy
+/// edits are validated and applied to `strategy`, contracts.toml edits are rejected.
+pub async fn watch_config(strategy: SharedStrategy) {
+    let mut strategy_modified = modified(STRATEGY_PATH);
+    let mut contracts_modified = modified(CONTRACTS_PATH);
+    let mut interval = tokio::time::interval(CONFIG_POLL_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let modified_at = modified(STRATEGY_PATH);
+        if modified_at != strategy_modified {
+            strategy_modified = modified_at;
+            match reload_strategy(&strategy) {
+                Ok(diff) if diff.is_empty() => info!("{} saved without changes", STRATEGY_PATH),
+                Ok(diff) => info!("strategy reloaded:\n  {}", diff.join("\n  ")),
+                Err(error) => error!("{} change rejected => {}", STRATEGY_PATH, error),
+            }
+        }
+
+        let modified_at = modified(CONTRACTS_PATH);
+        if modified_at != contracts_modified {
+            contracts_modified = modified_at;
+            let checked = fs::read_to_string(CONTRACTS_PATH)
+                .map_err(anyhow::Error::from)
+                .and_then(|config| check_contracts_reload(*CONTRACT, &config));
+
+            if let Err(error) = checked {
+                error!("{} change rejected => {}", CONTRACTS_PATH, error);
+            }
+        }
+    }
+}
+
+/// Loads the edited strategy.toml, keeping the running profile, and swaps it in.
+/// Returns the changed fields, nothing is applied if any check fails.
+pub fn reload_strategy(strategy: &SharedStrategy) -> Result<Vec<String>> {
+    let profile = strategy.current().profile;
+    let updated = load_strategy(Some(&profile))?;
+
+    strategy.reload(updated)
+}
+
+/// Chains, RPC urls and contracts are wired into the running event loops, so an edited
+/// contracts.toml is only accepted when it changes nothing that was loaded
+pub fn check_contracts_reload(running: &ContractAddressMap, config: &str) -> Result<()> {
+    let updated = ContractAddressMap::from_toml(config)?;
+
+    let mut chains: Vec<&Chain> = running
+        .addresses
+        .keys()
+        .chain(updated.addresses.keys())
+        .chain(running.unavailable.keys())
+        .chain(updated.unavailable.keys())
+        .collect();
+    chains.sort_by_key(|chain| chain.to_string());
+    chains.dedup();
+
+    let mut changes: Vec<String> = vec![];
+    for chain in chains {
+        match (running.addresses.get(chain), updated.addresses.get(chain)) {
+            (Some(running), Some(updated)) => {
+                let fields = running.changed_fields(updated);
+                if !fields.is_empty() {
+                    changes.push(format!("[{}] {}", chain, fields.join(", ")));
+                }
+            }
+            (Some(_), None) => changes.push(format!("[{}] removed", chain)),
+            (None, Some(_)) => changes.push(format!("[{}] added", chain)),
+            (None, None) => {}
+        }
+    }
+
+    if !changes.is_empty() {
+        return Err(anyhow!(
+            "contracts can't change at runtime, restart to apply: {}",
+            changes.join("; ")
+        ));
+    }
+
+    Ok(())
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
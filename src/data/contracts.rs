@@ -1,108 +1,516 @@
-use ethers::types::Chain;
-use once_cell::sync::Lazy;
+use crate::utils::secrets::{interpolate_env, InterpolationError};
+use anyhow::{anyhow, Result};
+use ethers::types::{Address, Chain};
+use once_cell::sync::{Lazy, OnceCell};
 use serde::Deserialize;
-use std::{collections::HashMap, fs};
-
-//*****************************************
-//*****************************************
-//*****************************************
-//*****************************************
-//*****************************************
-// CHANGE THIS VALUE TO SET CHAIN FOR BUILD
-pub const CHAIN: Chain = Chain::Mainnet;
-//*****************************************
-//*****************************************
-//*****************************************
-//*****************************************
-//*****************************************
+use std::{collections::HashMap, fs, future::Future};
+use url::Url;
+
+/// chain used when neither --chain nor CHAIN is given
+pub const DEFAULT_CHAIN: Chain = Chain::Mainnet;
+
+pub const CONTRACTS_PATH: &str = "contracts.toml";
+
+// SET ONCE AT STARTUP BY load_contracts
+static CONTRACTS: OnceCell<ContractAddressMap> = OnceCell::new();
+
+// SET ONCE AT STARTUP BY select_chain
+static CHAIN: OnceCell<Chain> = OnceCell::new();
+
+tokio::task_local! {
+    // SET PER EVENT LOOP BY with_chain WHEN SEVERAL CHAINS RUN IN ONE PROCESS
+    static CHAIN_SCOPE: Chain;
+}
+
 pub const ETH: &str = "0xEeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE";
 pub const BTC: &str = "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB";
 pub const USD: &str = "0x0000000000000000000000000000000000000348";
 
+/// Token new tokens can be paired with, anything not quoted in WETH is routed through
+/// the `weth_pool_fee` v3 pool (or the v2 pair) between it and WETH
+#[derive(Clone, Debug, PartialEq)]
+pub struct QuoteToken {
+    pub symbol: String,
+    pub address: Address,
+    pub decimals: u8,
+    pub weth_pool_fee: u32,
+}
+
+/// Contracts of one chain, parsed and checked once when contracts.toml is loaded. The
+/// optional dex contracts turn off the features that need them when left out.
+// no Debug, the urls can carry api keys
+#[derive(Clone)]
 pub struct ContractAddresses {
-    pub weth: String,
-    pub link: String,
-    pub uniswap_swap_router: String,
-    pub uniswap_v2_router: String,
-    pub uniswap_v2_factory: String,
-    pub uniswap_factory: String,
-    pub uniswap_quoter: String,
-    pub ws_url: String,
-    pub http_url: String,
+    pub weth: Address,
+    pub link: Address,
+    pub uniswap_swap_router: Address,
+    pub uniswap_factory: Address,
+    pub uniswap_quoter: Address,
+    /// needed to decode pending v3 mints
+    pub uniswap_position_manager: Option<Address>,
+    /// needed for v2 pair sniping
+    pub uniswap_v2_router: Option<Address>,
+    pub uniswap_v2_factory: Option<Address>,
+    pub ws_url: Url,
+    pub http_url: Url,
+    /// defaults to just WETH
+    pub quote_tokens: Vec<QuoteToken>,
+}
+
+impl ContractAddresses {
+    pub fn quote_token(&self, address: Address) -> Option<&QuoteToken> {
+        self.quote_tokens
+            .iter()
+            .find(|quote_token| quote_token.address == address)
+    }
+
+    pub fn has_uniswap_v2(&self) -> bool {
+        self.uniswap_v2_factory.is_some() && self.uniswap_v2_router.is_some()
+    }
+
+    pub fn uniswap_v2_factory_address(&self) -> Result<Address> {
+        required_address("uniswap_v2_factory", self.uniswap_v2_factory)
+    }
+
+    pub fn uniswap_v2_router_address(&self) -> Result<Address> {
+        required_address("uniswap_v2_router", self.uniswap_v2_router)
+    }
+
+    /// names of the fields that differ in `other`
+    pub fn changed_fields(&self, other: &ContractAddresses) -> Vec<&'static str> {
+        let fields = [
+            ("weth", self.weth == other.weth),
+            ("link", self.link == other.link),
+            (
+                "uniswap_swap_router",
+                self.uniswap_swap_router == other.uniswap_swap_router,
+            ),
+            (
+                "uniswap_factory",
+                self.uniswap_factory == other.uniswap_factory,
+            ),
+            (
+                "uniswap_quoter",
+                self.uniswap_quoter == other.uniswap_quoter,
+            ),
+            (
+                "uniswap_position_manager",
+                self.uniswap_position_manager == other.uniswap_position_manager,
+            ),
+            (
+                "uniswap_v2_router",
+                self.uniswap_v2_router == other.uniswap_v2_router,
+            ),
+            (
+                "uniswap_v2_factory",
+                self.uniswap_v2_factory == other.uniswap_v2_factory,
+            ),
+            ("ws_url", self.ws_url == other.ws_url),
+            ("http_url", self.http_url == other.http_url),
+            ("quote_tokens", self.quote_tokens == other.quote_tokens),
+        ];
+
+        fields
+            .into_iter()
+            .filter(|(_, unchanged)| !unchanged)
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    /// checks every field of one chain table, pushing what is wrong to `problems` and the
+    /// `${VAR}`s that are not set to `unset_vars`
+    fn from_raw(
+        chain: &str,
+        raw: RawContractAddresses,
+        problems: &mut Vec<String>,
+        unset_vars: &mut Vec<String>,
+    ) -> Option<Self> {
+        let mut check = FieldCheck {
+            chain,
+            problems,
+            unset_vars,
+        };
+
+        let weth = check.address("weth", raw.weth);
+        let link = check.address("link", raw.link);
+        let uniswap_swap_router = check.address("uniswap_swap_router", raw.uniswap_swap_router);
+        let uniswap_factory = check.address("uniswap_factory", raw.uniswap_factory);
+        let uniswap_quoter = check.address("uniswap_quoter", raw.uniswap_quoter);
+        let uniswap_position_manager =
+            check.optional_address("uniswap_position_manager", raw.uniswap_position_manager);
+        let uniswap_v2_router = check.optional_address("uniswap_v2_router", raw.uniswap_v2_router);
+        let uniswap_v2_factory =
+            check.optional_address("uniswap_v2_factory", raw.uniswap_v2_factory);
+        let ws_url = check.url("ws_url", raw.ws_url, &["ws", "wss"]);
+        let http_url = check.url("http_url", raw.http_url, &["http", "https"]);
+
+        if uniswap_v2_router.is_some() != uniswap_v2_factory.is_some() {
+            check.problem("uniswap_v2_router and uniswap_v2_factory have to be set together");
+        }
+
+        let mut unknown_keys: Vec<&String> = raw.unknown.keys().collect();
+        unknown_keys.sort();
+        for key in unknown_keys {
+            check.problem(&format!("unknown key {}", key));
+        }
+
+        let mut quote_tokens: Vec<QuoteToken> = vec![];
+        for quote_token in raw.quote_tokens {
+            let name = format!("quote token {}", quote_token.symbol);
+            let Some(address) = check.address(&name, Some(quote_token.address)) else {
+                continue;
+            };
+            if Some(address) != weth && quote_token.weth_pool_fee == 0 {
+                check.problem(&format!("{} needs a weth_pool_fee", name));
+            }
+
+            quote_tokens.push(QuoteToken {
+                symbol: quote_token.symbol,
+                address,
+                decimals: quote_token.decimals,
+                weth_pool_fee: quote_token.weth_pool_fee,
+            });
+        }
+
+        let weth = weth?;
+        if quote_tokens.is_empty() {
+            quote_tokens.push(QuoteToken {
+                symbol: "WETH".to_string(),
+                address: weth,
+                decimals: 18,
+                weth_pool_fee: 0,
+            });
+        }
+
+        Some(Self {
+            weth,
+            link: link?,
+            uniswap_swap_router: uniswap_swap_router?,
+            uniswap_factory: uniswap_factory?,
+            uniswap_quoter: uniswap_quoter?,
+            uniswap_position_manager: uniswap_position_manager?,
+            uniswap_v2_router: uniswap_v2_router?,
+            uniswap_v2_factory: uniswap_v2_factory?,
+            ws_url: ws_url?,
+            http_url: http_url?,
+            quote_tokens,
+        })
+    }
+}
+
+fn required_address(name: &str, address: Option<Address>) -> Result<Address> {
+    match address {
+        Some(address) => Ok(address),
+        None => Err(anyhow!(
+            "{} is not configured for {}",
+            name,
+            current_chain()?
+        )),
+    }
+}
+
+/// one chain table of contracts.toml as written, before anything is checked
+#[derive(Deserialize)]
+struct RawContractAddresses {
+    weth: Option<String>,
+    link: Option<String>,
+    uniswap_swap_router: Option<String>,
+    uniswap_factory: Option<String>,
+    uniswap_quoter: Option<String>,
+    uniswap_position_manager: Option<String>,
+    uniswap_v2_router: Option<String>,
+    uniswap_v2_factory: Option<String>,
+    ws_url: Option<String>,
+    http_url: Option<String>,
+    #[serde(default)]
+    quote_tokens: Vec<RawQuoteToken>,
+    // kept to report typos, a misspelled optional contract would silently turn a feature off
+    #[serde(flatten)]
+    unknown: HashMap<String, toml::Value>,
+}
+
+#[derive(Deserialize)]
+struct RawQuoteToken {
+    symbol: String,
+    address: String,
+    decimals: u8,
+    #[serde(default)]
+    weth_pool_fee: u32,
+}
+
+/// Parses the fields of one chain table, collecting every problem instead of stopping at
+/// the first one. Optional fields come back as `Some(None)` when left out, `None` means
+/// the field is invalid.
+struct FieldCheck<'a> {
+    chain: &'a str,
+    problems: &'a mut Vec<String>,
+    unset_vars: &'a mut Vec<String>,
+}
+
+impl FieldCheck<'_> {
+    fn problem(&mut self, problem: &str) {
+        self.problems.push(format!("[{}] {}", self.chain, problem));
+    }
+
+    fn required(&mut self, name: &str, value: Option<String>) -> Option<String> {
+        match value {
+            Some(value) => self.interpolated(name, &value),
+            None => {
+                self.problem(&format!("{} is missing", name));
+                None
+            }
+        }
+    }
+
+    /// resolves the `${VAR}`s in `value`, errors never echo the value since it may hold a
+    /// secret next to the variables
+    fn interpolated(&mut self, name: &str, value: &str) -> Option<String> {
+        match interpolate_env(value) {
+            Ok(value) => Some(value),
+            Err(InterpolationError::UnsetVars(vars)) => {
+                self.unset_vars.extend(vars);
+                None
+            }
+            Err(error) => {
+                self.problem(&format!("{} => {}", name, error));
+                None
+            }
+        }
+    }
+
+    fn address(&mut self, name: &str, value: Option<String>) -> Option<Address> {
+        let value = self.required(name, value)?;
+        self.parse_address(name, &value)
+    }
+
+    fn optional_address(&mut self, name: &str, value: Option<String>) -> Option<Option<Address>> {
+        match value {
+            Some(value) => {
+                let value = self.interpolated(name, &value)?;
+                self.parse_address(name, &value).map(Some)
+            }
+            None => Some(None),
+        }
+    }
+
+    fn parse_address(&mut self, name: &str, value: &str) -> Option<Address> {
+        let address = value.trim();
+        let is_hex = address.len() == 42
+            && address.starts_with("0x")
+            && address[2..].chars().all(|c| c.is_ascii_hexdigit());
+
+        match address.parse::<Address>() {
+            Ok(address) if is_hex => Some(address),
+            _ => {
+                self.problem(&format!("{} {:?} is not an address", name, value));
+                None
+            }
+        }
+    }
+
+    fn url(&mut self, name: &str, value: Option<String>, schemes: &[&str]) -> Option<Url> {
+        let value = self.required(name, value)?;
+
+        match Url::parse(value.trim()) {
+            Ok(url) if schemes.contains(&url.scheme()) => Some(url),
+            Ok(url) => {
+                self.problem(&format!(
+                    "{} has scheme {}, expected {}",
+                    name,
+                    url.scheme(),
+                    schemes.join(" or ")
+                ));
+                None
+            }
+            Err(error) => {
+                self.problem(&format!("{} is not a url => {}", name, error));
+                None
+            }
+        }
+    }
 }
 
 pub struct ContractAddressMap {
     pub addresses: HashMap<Chain, ContractAddresses>,
+    /// chains left out because the `${VAR}`s they use are not set, with those variables
+    pub unavailable: HashMap<Chain, Vec<String>>,
 }
 
 impl ContractAddressMap {
-    fn new(chains: Chains) -> Self {
+    /// Parses a contracts.toml with one table per chain, keyed by chain name or chain id.
+    /// The error lists every problem found, not just the first. A chain using `${VAR}`s
+    /// that are not set is only unavailable, so it doesn't stop the other chains.
+    pub fn from_toml(config: &str) -> Result<Self> {
+        let chains: HashMap<String, RawContractAddresses> = toml::from_str(config)?;
+        let mut chains: Vec<(String, RawContractAddresses)> = chains.into_iter().collect();
+        chains.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let mut problems: Vec<String> = vec![];
         let mut addresses = HashMap::<Chain, ContractAddresses>::new();
-        addresses.insert(
-            Chain::Base,
-            ContractAddresses {
-                uniswap_factory: chains.base.uniswap_factory,
-                uniswap_swap_router: chains.base.uniswap_swap_router,
-                uniswap_v2_factory: chains.base.uniswap_v2_factory,
-                uniswap_v2_router: chains.base.uniswap_v2_router,
-                uniswap_quoter: chains.base.uniswap_quoter,
-                weth: chains.base.weth,
-                link: chains.base.link,
-                ws_url: chains.base.ws_url,
-                http_url: chains.base.http_url,
-            },
-        );
-        addresses.insert(
-            Chain::Mainnet,
-            ContractAddresses {
-                uniswap_factory: chains.mainnet.uniswap_factory,
-                uniswap_swap_router: chains.mainnet.uniswap_swap_router,
-                uniswap_quoter: chains.mainnet.uniswap_quoter,
-                uniswap_v2_factory: chains.mainnet.uniswap_v2_factory,
-                uniswap_v2_router: chains.mainnet.uniswap_v2_router,
-                weth: chains.mainnet.weth,
-                link: chains.mainnet.link,
-                ws_url: chains.mainnet.ws_url,
-                http_url: chains.mainnet.http_url,
-            },
-        );
-        Self { addresses }
+        let mut unavailable = HashMap::<Chain, Vec<String>>::new();
+        let mut configured_chains: Vec<Chain> = vec![];
+
+        for (name, raw) in chains {
+            let chain = match parse_chain(&name) {
+                Ok(chain) => chain,
+                Err(error) => {
+                    problems.push(format!("[{}] {}", name, error));
+                    continue;
+                }
+            };
+
+            if configured_chains.contains(&chain) {
+                problems.push(format!("[{}] chain {} is configured twice", name, chain));
+                continue;
+            }
+            configured_chains.push(chain);
+
+            let mut unset_vars: Vec<String> = vec![];
+            let contracts = ContractAddresses::from_raw(&name, raw, &mut problems, &mut unset_vars);
+
+            if !unset_vars.is_empty() {
+                unset_vars.sort();
+                unset_vars.dedup();
+                unavailable.insert(chain, unset_vars);
+            } else if let Some(contracts) = contracts {
+                addresses.insert(chain, contracts);
+            }
+        }
+
+        if configured_chains.is_empty() {
+            problems.push("no chains configured".to_string());
+        }
+
+        if !problems.is_empty() {
+            return Err(anyhow!(
+                "{} problem(s) in contracts config:\n  {}",
+                problems.len(),
+                problems.join("\n  ")
+            ));
+        }
+
+        Ok(Self {
+            addresses,
+            unavailable,
+        })
     }
 
+    /// Addresses of the current chain, see `current_chain`. Panics outside a chain, looking
+    /// up contracts without knowing the chain is a bug.
     pub fn get_address(&self) -> &ContractAddresses {
-        self.addresses.get(&CHAIN).expect("Chain not supported")
+        // select_chain and with_chain only get chains that are in the map
+        current_chain()
+            .and_then(|chain| self.get_address_for(chain))
+            .unwrap_or_else(|error| panic!("{}", error))
     }
-}
 
-#[derive(Deserialize)]
-struct Chains {
-    base: ChainContracts,
-    mainnet: ChainContracts,
+    pub fn get_address_for(&self, chain: Chain) -> Result<&ContractAddresses> {
+        if let Some(unset_vars) = self.unavailable.get(&chain) {
+            return Err(anyhow!(
+                "chain {} needs environment variable(s) that are not set: {}",
+                chain,
+                unset_vars.join(", ")
+            ));
+        }
+
+        self.addresses.get(&chain).ok_or_else(|| {
+            let mut supported: Vec<String> = self
+                .addresses
+                .keys()
+                .map(|chain| chain.to_string())
+                .collect();
+            supported.sort();
+
+            anyhow!(
+                "chain {} is not supported, supported chains are: {}",
+                chain,
+                supported.join(", ")
+            )
+        })
+    }
 }
 
-#[derive(Deserialize)]
-struct ChainContracts {
-    uniswap_swap_router: String,
-    uniswap_factory: String,
-    uniswap_quoter: String,
-    uniswap_v2_router: String,
-    uniswap_v2_factory: String,
-    weth: String,
-    link: String,
-    ws_url: String,
-    http_url: String,
+/// Makes the chain `name` refers to the chain for this run. Fails if the chain has no
+/// contracts or another chain is already in use.
+pub fn select_chain(name: &str) -> Result<Chain> {
+    let chain = parse_supported_chain(name)?;
+
+    let selected_chain = *CHAIN.get_or_init(|| chain);
+    if selected_chain != chain {
+        return Err(anyhow!(
+            "cannot switch to {}, already running on {}",
+            chain,
+            selected_chain
+        ));
+    }
+
+    Ok(chain)
 }
 
-impl Chains {
-    fn load() -> Self {
-        let config = fs::read_to_string("contracts.toml").expect("failed to read toml file");
-        toml::from_str(&config).expect("failed to parse toml to chain config")
+/// parses a chain name ("mainnet", "base") or chain id
+pub fn parse_chain(name: &str) -> Result<Chain> {
+    let name = name.trim();
+
+    match name.parse::<u64>() {
+        Ok(chain_id) => {
+            Chain::try_from(chain_id).map_err(|_| anyhow!("unknown chain id {}", chain_id))
+        }
+        Err(_) => name
+            .to_lowercase()
+            .parse::<Chain>()
+            .map_err(|_| anyhow!("unknown chain {}", name)),
     }
 }
 
-// CREATE GLOBAL INSTANCE OF ALL CONTRACT ADDRESS FOR A GIVEN CHAIN
-pub static CONTRACT: Lazy<ContractAddressMap> = Lazy::new(|| {
-    let chains = Chains::load();
+/// parses `name` and makes sure the chain has contracts configured
+pub fn parse_supported_chain(name: &str) -> Result<Chain> {
+    let chain = parse_chain(name)?;
+    CONTRACT.get_address_for(chain)?;
+
+    Ok(chain)
+}
+
+/// every chain with contracts configured, sorted by name
+pub fn supported_chains() -> Vec<Chain> {
+    let mut chains: Vec<Chain> = CONTRACT.addresses.keys().copied().collect();
+    chains.sort_by_key(|chain| chain.to_string());
+    chains
+}
+
+/// Runs `future` with `chain` as its current chain, so contracts, the token registry and
+/// log lines inside it all belong to that chain
+pub async fn with_chain<F: Future>(chain: Chain, future: F) -> F::Output {
+    CHAIN_SCOPE.scope(chain, future).await
+}
+
+/// chain of the enclosing `with_chain`, else the one picked with `select_chain`
+pub fn scoped_chain() -> Option<Chain> {
+    CHAIN_SCOPE
+        .try_with(|chain| *chain)
+        .ok()
+        .or_else(|| CHAIN.get().copied())
+}
+
+/// Same as `scoped_chain` but an error when there is no chain. Nothing is picked as a
+/// fallback, that would pin the whole process to it.
+pub fn current_chain() -> Result<Chain> {
+    scoped_chain().ok_or_else(|| {
+        anyhow!("no chain selected, run inside with_chain or call select_chain first")
+    })
+}
+
+/// Reads and checks contracts.toml, once. Called first thing at startup so a broken
+/// config is reported as an error instead of a panic deep inside the bot.
+pub fn load_contracts() -> Result<&'static ContractAddressMap> {
+    CONTRACTS.get_or_try_init(|| {
+        let config = fs::read_to_string(CONTRACTS_PATH)
+            .map_err(|error| anyhow!("failed to read {} => {}", CONTRACTS_PATH, error))?;
+
+        ContractAddressMap::from_toml(&config)
+    })
+}
 
-    ContractAddressMap::new(chains)
-});
+// CREATE GLOBAL INSTANCE OF ALL CONTRACT ADDRESS FOR EVERY CONFIGURED CHAIN
+// main calls load_contracts first, so this only panics when that was skipped
+pub static CONTRACT: Lazy<&'static ContractAddressMap> =
+    Lazy::new(|| load_contracts().unwrap_or_else(|error| panic!("{}", error)));
@@ -0,0 +1,129 @@
+use ethers::types::U256;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Where a token is in its life, from the pool being seen to the position being closed.
+/// Only the moves in `can_become` are allowed, every move is kept in the token's history.
+#[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TokenState {
+    /// pool / pair seen, token metadata loaded
+    #[default]
+    Discovered,
+    /// no liquidity in the pool yet, waiting for a Mint
+    AwaitingLiquidity,
+    /// liquidity is in, the token is checked and bought
+    Validating,
+    /// failed a check and is never bought
+    Rejected {
+        reason: String,
+    },
+    /// `amount` held since the block at `bought_at`
+    Bought {
+        amount: U256,
+        bought_at: u32,
+    },
+    /// hold time is up, `attempts` sells have been sent
+    Selling {
+        amount: U256,
+        attempts: u32,
+    },
+    Sold,
+    /// every sell failed, or the position could not be found after a restart,
+    /// `amount` is what was last known to be held
+    Stuck {
+        amount: U256,
+        reason: String,
+    },
+}
+
+impl TokenState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TokenState::Discovered => "discovered",
+            TokenState::AwaitingLiquidity => "awaiting liquidity",
+            TokenState::Validating => "validating",
+            TokenState::Rejected { .. } => "rejected",
+            TokenState::Bought { .. } => "bought",
+            TokenState::Selling { .. } => "selling",
+            TokenState::Sold => "sold",
+            TokenState::Stuck { .. } => "stuck",
+        }
+    }
+
+    /// whether the lifecycle allows moving from this state to `next`
+    pub fn can_become(&self, next: &TokenState) -> bool {
+        use TokenState::*;
+
+        matches!(
+            (self, next),
+            (Discovered, AwaitingLiquidity | Validating | Rejected { .. })
+                | (AwaitingLiquidity, Validating | Rejected { .. })
+                | (Validating, Bought { .. } | Rejected { .. })
+                | (Bought { .. }, Selling { .. } | Stuck { .. })
+                | (Selling { .. }, Selling { .. } | Sold | Stuck { .. })
+        )
+    }
+
+    /// a position is held and waiting to be sold
+    pub fn is_open(&self) -> bool {
+        matches!(self, TokenState::Bought { .. } | TokenState::Selling { .. })
+    }
+
+    /// rejected, sold and stuck tokens are never touched again
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            TokenState::Rejected { .. } | TokenState::Sold | TokenState::Stuck { .. }
+        )
+    }
+
+    /// liquidity has been seen in the pool
+    pub fn is_tradable(&self) -> bool {
+        matches!(
+            self,
+            TokenState::Validating | TokenState::Bought { .. } | TokenState::Selling { .. }
+        )
+    }
+
+    /// tokens the anvil account holds in this state
+    pub fn amount_held(&self) -> U256 {
+        match self {
+            TokenState::Bought { amount, .. }
+            | TokenState::Selling { amount, .. }
+            | TokenState::Stuck { amount, .. } => *amount,
+            _ => U256::zero(),
+        }
+    }
+}
+
+impl fmt::Display for TokenState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenState::Rejected { reason } => write!(f, "rejected ({})", reason),
+            TokenState::Bought { amount, .. } => write!(f, "bought {}", amount),
+            TokenState::Selling { attempts, .. } => write!(f, "selling (attempt {})", attempts),
+            TokenState::Stuck { reason, .. } => write!(f, "stuck ({})", reason),
+            state => write!(f, "{}", state.name()),
+        }
+    }
+}
+
+/// a state a token entered and the unix time it did
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateChange {
+    pub state: TokenState,
+    pub at: u64,
+}
+
+impl StateChange {
+    pub fn now(state: TokenState) -> Self {
+        let at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+
+        Self { state, at }
+    }
+}
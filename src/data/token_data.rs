@@ -1,141 +1,465 @@
-use crate::abi::erc20::ERC20;
-use crate::swap::token_price::get_token_weth_liquidity;
+use crate::abi::uniswap_pool::UNISWAP_V3_POOL;
+use crate::multicall::Multicall;
+use crate::swap::launch_price::{launch_rejection_reason, read_launch_price, LaunchPriceBounds};
+use crate::swap::token_price::get_quote_liquidities;
+use crate::swap::transfer_tax::TokenTaxes;
+use crate::uniswap_v2_events::PairCreatedEvent;
 use crate::uniswap_v3_events::PoolCreatedEvent;
-use crate::utils::type_conversion::address_to_string;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use ethers::providers::{Provider, Ws};
-use ethers::types::Address;
+use ethers::types::{Address, Chain, U256};
 use futures::lock::Mutex;
-use log::warn;
-use once_cell::sync::Lazy;
+use log::{error, warn};
 use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
 
 use super::contracts::CONTRACT;
-use super::tokens::Erc20Token;
-
-static TOKEN_HASH: Lazy<Arc<Mutex<HashMap<String, Erc20Token>>>> =
-    Lazy::new(|| Arc::new(Mutex::new(HashMap::<String, Erc20Token>::new())));
-
-pub async fn get_and_save_erc20_by_token_address(
-    pool_created_event: &PoolCreatedEvent,
-    client: &Arc<Provider<Ws>>,
-) -> Result<Option<Erc20Token>> {
-    let token_data_hash = Arc::clone(&TOKEN_HASH);
-    let mut tokens = token_data_hash.lock().await;
-    let weth_address: Address = CONTRACT.get_address().weth.parse()?;
-
-    // find address of new token
-    let (token_address, is_token_0) = if weth_address == pool_created_event.token0 {
-        (pool_created_event.token1, false)
-    } else if weth_address == pool_created_event.token1 {
-        (pool_created_event.token0, true)
-    } else {
-        warn!("not weth pool, skipping");
-        return Ok(None);
-    };
-
-    // TODO - VALIDATE TOKEN HERE - IF SCAM exit out
-
-    let token_address_string = address_to_string(token_address).to_lowercase();
-
-    // make sure token is not already in hashmap
-    if tokens.contains_key(&token_address_string) {
-        let token = tokens.get(&token_address_string).unwrap();
-        return Ok(Some(token.clone()));
-    }
-
-    let token_contract = ERC20::new(token_address, client.clone());
-
-    // get basic toke data
-    let symbol = token_contract.symbol().call().await?;
-    let decimals = token_contract.decimals().call().await?;
-    let name = token_contract.name().call().await?;
-
-    let token = Erc20Token {
-        name,
-        symbol,
-        decimals,
-        fee: pool_created_event.fee,
-        address: token_address,
-        pool_address: pool_created_event.pool,
-        is_token_0,
-        ..Default::default()
-    };
-
-    tokens.insert(token_address_string, token.clone());
-
-    Ok(Some(token))
-}
+use super::lifecycle::{StateChange, TokenState};
+use super::token_metadata::MetadataCalls;
+use super::token_risk::scan_token_risk;
+use super::token_store::{JournalEntry, TokenJournal};
+use super::tokens::{Dex, Erc20Token};
 
-pub async fn get_tokens() -> HashMap<String, Erc20Token> {
-    let token_data_hash = Arc::clone(&TOKEN_HASH);
-    let tokens = token_data_hash.lock().await;
+/// Every token one chain's bot knows about, keyed by token address. Clones share the same
+/// tokens, so the registry is created once per chain and handed to everything that needs it.
+#[derive(Clone, Default)]
+pub struct TokenRegistry {
+    inner: Arc<Mutex<RegistryState>>,
+}
 
-    tokens.clone()
+#[derive(Default)]
+struct RegistryState {
+    tokens: HashMap<Address, Erc20Token>,
+    /// every change is written through to it when the registry is persisted
+    journal: Option<TokenJournal>,
 }
 
-pub async fn get_token(token_address: Address) -> Option<Erc20Token> {
-    let token_data_hash = Arc::clone(&TOKEN_HASH);
-    let tokens = token_data_hash.lock().await;
-    let token_address_string = address_to_string(token_address).to_lowercase();
+impl RegistryState {
+    fn upsert(&mut self, token: Erc20Token) {
+        self.persist(JournalEntry::Upsert {
+            token: Box::new(token.clone()),
+        });
+        self.tokens.insert(token.address, token);
+    }
+
+    fn remove(&mut self, token_address: Address) -> Option<Erc20Token> {
+        let token = self.tokens.remove(&token_address)?;
+        self.persist(JournalEntry::Remove {
+            address: token.address,
+        });
 
-    if let Some(token) = tokens.get(&token_address_string) {
-        Some(token.clone())
-    } else {
-        None
+        Some(token)
+    }
+
+    fn persist(&mut self, entry: JournalEntry) {
+        if let Some(journal) = self.journal.as_mut() {
+            if let Err(error) = journal.append(&entry) {
+                error!("could not persist token change => {}", error);
+            }
+        }
     }
 }
 
-pub async fn check_all_tokens_and_update_if_are_tradable(
-    client: &Arc<Provider<Ws>>,
-) -> anyhow::Result<()> {
-    let token_data_hash = Arc::clone(&TOKEN_HASH);
-    let mut tokens = token_data_hash.lock().await;
+impl TokenRegistry {
+    /// empty registry that only lives in memory
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads `chain`'s registry from its journal in `dir` and writes every later change
+    /// through to it. Returns the registry and the number of tokens loaded.
+    pub fn open(dir: impl AsRef<Path>, chain: Chain) -> Result<(Self, usize)> {
+        let (journal, tokens) = TokenJournal::open(TokenJournal::path(dir, chain))?;
+        let stored = tokens.len();
+
+        let registry = Self {
+            inner: Arc::new(Mutex::new(RegistryState {
+                tokens,
+                journal: Some(journal),
+            })),
+        };
+
+        Ok((registry, stored))
+    }
+
+    pub async fn get_and_save_erc20_by_token_address(
+        &self,
+        pool_created_event: &PoolCreatedEvent,
+        bounds: &LaunchPriceBounds,
+        client: &Arc<Provider<Ws>>,
+    ) -> Result<Option<Erc20Token>> {
+        self.save_erc20_token(
+            pool_created_event.token0,
+            pool_created_event.token1,
+            pool_created_event.fee,
+            pool_created_event.pool,
+            Dex::UniswapV3,
+            bounds,
+            client,
+        )
+        .await
+    }
+
+    pub async fn get_and_save_v2_erc20_by_token_address(
+        &self,
+        pair_created_event: &PairCreatedEvent,
+        bounds: &LaunchPriceBounds,
+        client: &Arc<Provider<Ws>>,
+    ) -> Result<Option<Erc20Token>> {
+        // v2 pairs have a fixed 0.3% fee that the router applies itself
+        self.save_erc20_token(
+            pair_created_event.token0,
+            pair_created_event.token1,
+            0,
+            pair_created_event.pair,
+            Dex::UniswapV2,
+            bounds,
+            client,
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn save_erc20_token(
+        &self,
+        token0: Address,
+        token1: Address,
+        fee: u32,
+        pool_address: Address,
+        dex: Dex,
+        bounds: &LaunchPriceBounds,
+        client: &Arc<Provider<Ws>>,
+    ) -> Result<Option<Erc20Token>> {
+        let addresses = CONTRACT.get_address();
+
+        // find address of new token and what it is quoted in
+        let (token_address, is_token_0, quote_token) =
+            match (addresses.quote_token(token0), addresses.quote_token(token1)) {
+                (Some(quote_token), None) => (token1, false, quote_token),
+                (None, Some(quote_token)) => (token0, true, quote_token),
+                (Some(_), Some(_)) => {
+                    warn!("pool between two quote tokens, skipping");
+                    return Ok(None);
+                }
+                (None, None) => {
+                    warn!("no quote token in pool, skipping");
+                    return Ok(None);
+                }
+            };
+
+        // make sure token is not already in hashmap, the lock is not held over the calls
+        // below so a slow node doesn't stall every other registry user
+        if let Some(token) = self.get_token(token_address).await {
+            return Ok(Some(token));
+        }
 
-    for token in tokens.values_mut() {
-        if !token.is_tradable {
-            // check liquidity
-            let token_liquidity = get_token_weth_liquidity(&token, client).await?;
+        let pool = UNISWAP_V3_POOL::new(pool_address, client.clone());
 
-            if token_liquidity > 0 {
-                token.is_tradable = true;
+        // get basic toke data, and the launch price of v3 pools, in one eth_call
+        let mut multicall = Multicall::new(client);
+        let metadata = MetadataCalls::add(&mut multicall, token_address, client);
+        let slot_0 = (dex == Dex::UniswapV3).then(|| multicall.add(pool.slot_0()));
+        let results = multicall.call().await?;
+
+        // pools created by the position manager are initialized in the same transaction
+        let (launch_sqrt_price_x96, launch_tick) = match slot_0 {
+            Some(slot_0) => read_launch_price(&results, &slot_0),
+            None => (U256::zero(), 0),
+        };
+        // non-standard tokens still get tracked, with fallbacks for what could not be read
+        let metadata = metadata.read(&results);
+
+        // capabilities are only reported here, buying is decided by the test trades
+        let risk = match scan_token_risk(token_address, client).await {
+            Ok(risk) => {
+                if !risk.capabilities.is_empty() {
+                    let capabilities: Vec<String> =
+                        risk.capabilities.iter().map(|c| c.to_string()).collect();
+                    warn!(
+                        "{} ({:?}) code can {}",
+                        metadata.name,
+                        token_address,
+                        capabilities.join(", ")
+                    );
+                }
+                Some(risk)
+            }
+            Err(error) => {
+                warn!("could not scan code of {:?} => {}", token_address, error);
+                None
             }
+        };
+
+        let mut token = Erc20Token {
+            name: metadata.name,
+            symbol: metadata.symbol,
+            decimals: metadata.decimals,
+            fee,
+            address: token_address,
+            pool_address,
+            dex,
+            is_token_0,
+            quote_token: quote_token.address,
+            quote_decimals: quote_token.decimals,
+            total_supply: metadata.total_supply,
+            launch_sqrt_price_x96,
+            launch_tick,
+            state: TokenState::Discovered,
+            history: vec![StateChange::now(TokenState::Discovered)],
+            missing_metadata: metadata.missing,
+            taxes: None,
+            risk,
+        };
+
+        let rejection_reason = launch_rejection_reason(&token, bounds, client).await?;
+
+        let mut registry = self.inner.lock().await;
+        // a second pool of the same token may have been saved while the calls ran
+        if let Some(token) = registry.tokens.get(&token_address) {
+            return Ok(Some(token.clone()));
+        }
+
+        // rejected tokens stay in the registry so a second pool for them is not checked again
+        if let Some(reason) = rejection_reason {
+            warn!(
+                "skipping {} ({:?}) => {}",
+                token.name, token_address, reason
+            );
+            token.transition(TokenState::Rejected { reason })?;
+            registry.upsert(token);
+            return Ok(None);
         }
+
+        registry.upsert(token.clone());
+
+        Ok(Some(token))
     }
 
-    Ok(())
-}
+    pub async fn get_tokens(&self) -> HashMap<Address, Erc20Token> {
+        self.inner.lock().await.tokens.clone()
+    }
 
-pub async fn remove_token(token_address: Address) -> Option<Erc20Token> {
-    let token_data_hash = Arc::clone(&TOKEN_HASH);
-    let mut tokens = token_data_hash.lock().await;
-    let token_address_string = address_to_string(token_address).to_lowercase();
+    pub async fn get_token(&self, token_address: Address) -> Option<Erc20Token> {
+        self.inner.lock().await.tokens.get(&token_address).cloned()
+    }
 
-    tokens.remove(&token_address_string)
-}
+    pub async fn get_token_state(&self, token_address: Address) -> Option<TokenState> {
+        self.get_token(token_address).await.map(|token| token.state)
+    }
 
-pub async fn is_token_tradable(token_address: Address) -> bool {
-    let token_data_hash = Arc::clone(&TOKEN_HASH);
-    let tokens = token_data_hash.lock().await;
-    let token_address_string = address_to_string(token_address).to_lowercase();
+    /// every state `token_address` went through, oldest first
+    pub async fn get_token_history(&self, token_address: Address) -> Option<Vec<StateChange>> {
+        self.get_token(token_address)
+            .await
+            .map(|token| token.history)
+    }
 
-    let token = tokens.get(&token_address_string).unwrap();
+    /// every token whose state matches `filter`
+    pub async fn get_tokens_in_state(
+        &self,
+        filter: impl Fn(&TokenState) -> bool,
+    ) -> Vec<Erc20Token> {
+        let registry = self.inner.lock().await;
 
-    token.is_tradable
-}
+        registry
+            .tokens
+            .values()
+            .filter(|token| filter(&token.state))
+            .cloned()
+            .collect()
+    }
 
-pub async fn update_token(updated_token: &Erc20Token) {
-    let token_data_hash = Arc::clone(&TOKEN_HASH);
-    let mut tokens = token_data_hash.lock().await;
-    let token_address = address_to_string(updated_token.address).to_lowercase();
+    /// pools on `dex` of the tokens still waiting for liquidity, sorted
+    pub async fn get_pools_awaiting_liquidity(&self, dex: Dex) -> Vec<Address> {
+        let mut pools: Vec<Address> = self
+            .get_tokens_in_state(|state| {
+                matches!(
+                    state,
+                    TokenState::Discovered | TokenState::AwaitingLiquidity
+                )
+            })
+            .await
+            .into_iter()
+            .filter(|token| token.dex == dex)
+            .map(|token| token.pool_address)
+            .collect();
+        pools.sort();
+        pools.dedup();
 
-    tokens.insert(token_address, updated_token.clone());
-}
+        pools
+    }
 
-pub async fn get_number_of_tokens() -> usize {
-    let token_data_hash = Arc::clone(&TOKEN_HASH);
-    let tokens = token_data_hash.lock().await;
+    /// Moves a tracked token to `next`, see `TokenState::can_become`. Returns the updated token.
+    pub async fn transition_token(
+        &self,
+        token_address: Address,
+        next: TokenState,
+    ) -> Result<Erc20Token> {
+        let mut registry = self.inner.lock().await;
 
-    tokens.len()
+        let mut token = registry
+            .tokens
+            .get(&token_address)
+            .cloned()
+            .ok_or_else(|| anyhow!("token {:?} is not tracked", token_address))?;
+        token.transition(next)?;
+        registry.upsert(token.clone());
+
+        Ok(token)
+    }
+
+    /// stores the taxes the test trades measured on the token
+    pub async fn record_taxes(
+        &self,
+        token_address: Address,
+        taxes: TokenTaxes,
+    ) -> Result<Erc20Token> {
+        let mut registry = self.inner.lock().await;
+
+        let mut token = registry
+            .tokens
+            .get(&token_address)
+            .cloned()
+            .ok_or_else(|| anyhow!("token {:?} is not tracked", token_address))?;
+        token.taxes = Some(taxes);
+        registry.upsert(token.clone());
+
+        Ok(token)
+    }
+
+    /// slow fallback for liquidity that arrived without a Mint log being seen,
+    /// liquidity is queried without holding the registry lock
+    pub async fn check_all_tokens_and_update_if_are_tradable(
+        &self,
+        client: &Arc<Provider<Ws>>,
+    ) -> anyhow::Result<()> {
+        let untradable_tokens = self
+            .get_tokens_in_state(|state| {
+                matches!(
+                    state,
+                    TokenState::Discovered | TokenState::AwaitingLiquidity
+                )
+            })
+            .await;
+
+        // liquidity of every pool in one eth_call, a pool that can't be read is retried next poll
+        let liquidities = get_quote_liquidities(&untradable_tokens, client).await?;
+
+        for (token, token_liquidity) in untradable_tokens.iter().zip(liquidities) {
+            match token_liquidity {
+                Ok(liquidity) if liquidity > 0 => {
+                    self.mark_token_tradable_by_pool_address(token.pool_address)
+                        .await;
+                }
+                Ok(_) => {}
+                Err(error) => warn!("could not read liquidity of {} => {}", token.name, error),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// moves the token traded on `pool_address` on to validating, returns it only if it was
+    /// still waiting for liquidity
+    pub async fn mark_token_tradable_by_pool_address(
+        &self,
+        pool_address: Address,
+    ) -> Option<Erc20Token> {
+        let mut registry = self.inner.lock().await;
+
+        let mut token = registry
+            .tokens
+            .values()
+            .find(|token| {
+                token.pool_address == pool_address
+                    && token.state.can_become(&TokenState::Validating)
+            })?
+            .clone();
+        token.transition(TokenState::Validating).ok()?;
+        registry.upsert(token.clone());
+
+        Some(token)
+    }
+
+    /// records the price a tracked v3 pool was initialized at, unless it already has one
+    pub async fn record_launch_price(
+        &self,
+        pool_address: Address,
+        sqrt_price_x96: U256,
+        tick: i32,
+    ) -> Option<Erc20Token> {
+        let mut registry = self.inner.lock().await;
+
+        let mut token = registry
+            .tokens
+            .values()
+            .find(|token| {
+                token.pool_address == pool_address && token.launch_sqrt_price_x96.is_zero()
+            })?
+            .clone();
+        token.launch_sqrt_price_x96 = sqrt_price_x96;
+        token.launch_tick = tick;
+        registry.upsert(token.clone());
+
+        Some(token)
+    }
+
+    pub async fn remove_token(&self, token_address: Address) -> Option<Erc20Token> {
+        self.inner.lock().await.remove(token_address)
+    }
+
+    /// Removes the token traded on `pool_address`, used when the log that created the pool is
+    /// reorged out. A token with an open position is marked stuck instead and a stuck one is
+    /// left alone, so the position is not lost. Returns the token as it was removed or kept.
+    pub async fn remove_token_by_pool_address(&self, pool_address: Address) -> Option<Erc20Token> {
+        let mut registry = self.inner.lock().await;
+
+        let mut token = registry
+            .tokens
+            .values()
+            .find(|token| token.pool_address == pool_address)
+            .cloned()?;
+
+        if token.state.is_open() {
+            let stuck = TokenState::Stuck {
+                amount: token.state.amount_held(),
+                reason: format!("pool {:?} was reorged out", pool_address),
+            };
+            match token.transition(stuck) {
+                Ok(()) => registry.upsert(token.clone()),
+                Err(error) => error!("could not mark {} stuck => {}", token.name, error),
+            }
+            return Some(token);
+        }
+
+        if matches!(token.state, TokenState::Stuck { .. }) {
+            return Some(token);
+        }
+
+        registry.remove(token.address)
+    }
+
+    pub async fn is_token_tradable(&self, token_address: Address) -> bool {
+        let registry = self.inner.lock().await;
+
+        let token = registry.tokens.get(&token_address).unwrap();
+
+        token.state.is_tradable()
+    }
+
+    pub async fn update_token(&self, updated_token: &Erc20Token) {
+        self.inner.lock().await.upsert(updated_token.clone());
+    }
+
+    /// tokens still being worked on, rejected, sold and stuck ones are not counted
+    pub async fn get_number_of_tokens(&self) -> usize {
+        let registry = self.inner.lock().await;
+
+        registry
+            .tokens
+            .values()
+            .filter(|token| !token.state.is_final())
+            .count()
+    }
 }
@@ -0,0 +1,143 @@
+use crate::abi::erc20::ERC20;
+use crate::multicall::{CallHandle, Multicall, MulticallResults};
+use ethers::abi::{self, ParamType, Token};
+use ethers::providers::Middleware;
+use ethers::types::{Address, Bytes, U256};
+use log::warn;
+use std::sync::Arc;
+
+/// longest name or symbol kept, launch tokens stuff whole messages into them
+pub const MAX_METADATA_CHARS: usize = 64;
+/// used when decimals() is missing, what nearly every launch token uses
+pub const DEFAULT_DECIMALS: u8 = 18;
+
+/// name, symbol, decimals and supply of a token as far as they could be read
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TokenMetadata {
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
+    pub total_supply: U256,
+    /// fields that reverted or returned nothing usable and were filled in with a fallback
+    pub missing: Vec<String>,
+}
+
+/// the four metadata calls of one token queued on a `Multicall`
+pub struct MetadataCalls {
+    address: Address,
+    name: CallHandle<String>,
+    symbol: CallHandle<String>,
+    decimals: CallHandle<u8>,
+    total_supply: CallHandle<U256>,
+}
+
+impl MetadataCalls {
+    pub fn add<M: Middleware + 'static>(
+        multicall: &mut Multicall<M>,
+        token_address: Address,
+        client: &Arc<M>,
+    ) -> Self {
+        let token_contract = ERC20::new(token_address, client.clone());
+
+        Self {
+            address: token_address,
+            name: multicall.add(token_contract.name()),
+            symbol: multicall.add(token_contract.symbol()),
+            decimals: multicall.add(token_contract.decimals()),
+            total_supply: multicall.add(token_contract.total_supply()),
+        }
+    }
+
+    /// Decodes whatever the token returned, tolerating bytes32 strings (MKR-style),
+    /// reverts and missing functions. Never fails, gaps are listed in `missing`.
+    pub fn read(&self, results: &MulticallResults) -> TokenMetadata {
+        let name = results
+            .get_raw(&self.name)
+            .ok()
+            .and_then(|data| decode_string(&data));
+        let symbol = results
+            .get_raw(&self.symbol)
+            .ok()
+            .and_then(|data| decode_string(&data));
+        let decimals = results
+            .get_raw(&self.decimals)
+            .ok()
+            .and_then(|data| decode_decimals(&data));
+        let total_supply = results
+            .get_raw(&self.total_supply)
+            .ok()
+            .and_then(|data| decode_uint(&data));
+
+        let missing: Vec<String> = [
+            ("name", name.is_none()),
+            ("symbol", symbol.is_none()),
+            ("decimals", decimals.is_none()),
+            ("total_supply", total_supply.is_none()),
+        ]
+        .into_iter()
+        .filter(|(_, is_missing)| *is_missing)
+        .map(|(field, _)| field.to_string())
+        .collect();
+
+        if !missing.is_empty() {
+            warn!(
+                "token {:?} is missing {}, using fallbacks",
+                self.address,
+                missing.join(", ")
+            );
+        }
+
+        // a name is needed for logs, borrow the other field or the address
+        let fallback = format!("{:?}", self.address);
+        TokenMetadata {
+            name: name.clone().or(symbol.clone()).unwrap_or(fallback.clone()),
+            symbol: symbol.or(name).unwrap_or(fallback),
+            decimals: decimals.unwrap_or(DEFAULT_DECIMALS),
+            total_supply: total_supply.unwrap_or_default(),
+            missing,
+        }
+    }
+}
+
+/// a `string` return, or a `bytes32` padded with zeros as older tokens return
+pub fn decode_string(data: &Bytes) -> Option<String> {
+    let raw = if data.len() == 32 {
+        let end = data.iter().position(|byte| *byte == 0).unwrap_or(32);
+        String::from_utf8_lossy(&data[..end]).into_owned()
+    } else {
+        match abi::decode(&[ParamType::String], data).ok()?.pop()? {
+            Token::String(raw) => raw,
+            _ => return None,
+        }
+    };
+
+    let sanitized = sanitize_metadata(&raw);
+    (!sanitized.is_empty()).then_some(sanitized)
+}
+
+/// Makes a token name safe to log: control characters are dropped, anything outside
+/// printable ASCII (lookalikes, right-to-left overrides, zero width joiners) becomes '?'
+pub fn sanitize_metadata(raw: &str) -> String {
+    raw.chars()
+        .filter(|char| !char.is_control())
+        .map(|char| {
+            if char.is_ascii_graphic() || char == ' ' {
+                char
+            } else {
+                '?'
+            }
+        })
+        .take(MAX_METADATA_CHARS)
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+fn decode_uint(data: &Bytes) -> Option<U256> {
+    // anything shorter is not an abi encoded uint, longer is tolerated like solidity does
+    (data.len() >= 32).then(|| U256::from_big_endian(&data[..32]))
+}
+
+fn decode_decimals(data: &Bytes) -> Option<u8> {
+    u8::try_from(decode_uint(data)?).ok()
+}
@@ -0,0 +1,205 @@
+use crate::utils::type_conversion::get_function_selector;
+use anyhow::{anyhow, Result};
+use ethers::providers::{Middleware, Provider, Ws};
+use ethers::types::Address;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+const PUSH1: u8 = 0x60;
+const PUSH4: u8 = 0x63;
+const PUSH32: u8 = 0x7f;
+const DELEGATECALL: u8 = 0xf4;
+
+/// something the token's owner can do to holders, found from the functions its code exposes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    /// addresses can be barred from transferring
+    Blacklist,
+    /// all transfers can be stopped
+    Pause,
+    /// supply can be increased
+    Mint,
+    /// buy, sell or transfer taxes can be changed
+    SetFee,
+    /// transaction or wallet size can be capped
+    MaxTx,
+    /// transfers only work once the owner opens trading or whitelists the holder
+    TransferGating,
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Capability::Blacklist => "blacklist",
+            Capability::Pause => "pause",
+            Capability::Mint => "mint",
+            Capability::SetFee => "set fee",
+            Capability::MaxTx => "max tx",
+            Capability::TransferGating => "transfer gating",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// signatures that give a token a risky capability, add new ones as they turn up in launches
+pub const RISKY_SIGNATURES: &[(Capability, &str)] = &[
+    (Capability::Blacklist, "blacklist(address)"),
+    (Capability::Blacklist, "blacklist(address,bool)"),
+    (Capability::Blacklist, "addToBlacklist(address)"),
+    (Capability::Blacklist, "setBlacklist(address,bool)"),
+    (Capability::Blacklist, "blacklistAddress(address,bool)"),
+    (Capability::Blacklist, "isBlacklisted(address)"),
+    (Capability::Blacklist, "addBot(address)"),
+    (Capability::Blacklist, "addBots(address[])"),
+    (Capability::Blacklist, "setBots(address[])"),
+    (Capability::Blacklist, "setBot(address,bool)"),
+    (Capability::Blacklist, "isBot(address)"),
+    (Capability::Pause, "pause()"),
+    (Capability::Pause, "unpause()"),
+    (Capability::Pause, "setPaused(bool)"),
+    (Capability::Mint, "mint(address,uint256)"),
+    (Capability::Mint, "mint(uint256)"),
+    (Capability::Mint, "mintTo(address,uint256)"),
+    (Capability::SetFee, "setFee(uint256)"),
+    (Capability::SetFee, "setFee(uint256,uint256)"),
+    (Capability::SetFee, "setFees(uint256,uint256)"),
+    (Capability::SetFee, "setTax(uint256)"),
+    (Capability::SetFee, "setTaxes(uint256,uint256)"),
+    (Capability::SetFee, "setBuyFee(uint256)"),
+    (Capability::SetFee, "setSellFee(uint256)"),
+    (Capability::SetFee, "setBuyTax(uint256)"),
+    (Capability::SetFee, "setSellTax(uint256)"),
+    (Capability::SetFee, "updateFees(uint256,uint256)"),
+    (Capability::SetFee, "setTaxFeePercent(uint256)"),
+    (Capability::MaxTx, "setMaxTxAmount(uint256)"),
+    (Capability::MaxTx, "setMaxTx(uint256)"),
+    (Capability::MaxTx, "setMaxWallet(uint256)"),
+    (Capability::MaxTx, "setMaxWalletSize(uint256)"),
+    (Capability::MaxTx, "updateMaxTxnAmount(uint256)"),
+    (Capability::MaxTx, "updateMaxWalletAmount(uint256)"),
+    (Capability::TransferGating, "enableTrading()"),
+    (Capability::TransferGating, "openTrading()"),
+    (Capability::TransferGating, "setTradingEnabled(bool)"),
+    (Capability::TransferGating, "setTradingOpen(bool)"),
+    (Capability::TransferGating, "setWhitelist(address,bool)"),
+    (Capability::TransferGating, "setCooldownEnabled(bool)"),
+];
+
+/// RISKY_SIGNATURES by selector
+static RISK_CATALOGUE: Lazy<HashMap<[u8; 4], (Capability, &'static str)>> = Lazy::new(|| {
+    RISKY_SIGNATURES
+        .iter()
+        .map(|(capability, signature)| {
+            let selector = get_function_selector(signature);
+            let selector: [u8; 4] = selector[..4].try_into().expect("selectors are 4 bytes");
+            (selector, (*capability, *signature))
+        })
+        .collect()
+});
+
+/// What a token's runtime code can do, from the function selectors it dispatches on
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RiskReport {
+    /// every capability found, without repeats
+    pub capabilities: Vec<Capability>,
+    /// the catalogue signatures behind them
+    pub signatures: Vec<String>,
+    /// distinct PUSH4 values in the code, selectors and the odd constant
+    pub selectors_found: usize,
+    pub code_size: usize,
+    /// code uses DELEGATECALL, e.g. a proxy, so capabilities can live in code not scanned here
+    pub delegates: bool,
+}
+
+impl RiskReport {
+    pub fn has(&self, capability: Capability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.capabilities.is_empty() && !self.delegates
+    }
+}
+
+/// Fetches the runtime code of `token_address` and scans it
+pub async fn scan_token_risk(
+    token_address: Address,
+    client: &Arc<Provider<Ws>>,
+) -> Result<RiskReport> {
+    let code = client.get_code(token_address, None).await?;
+
+    if code.is_empty() {
+        return Err(anyhow!("{:?} has no code", token_address));
+    }
+
+    Ok(scan_bytecode(&code))
+}
+
+/// Walks the opcodes of `code`, skipping push data, and matches every PUSH4 against the catalogue
+pub fn scan_bytecode(code: &[u8]) -> RiskReport {
+    let code_size = code.len();
+    let code = strip_metadata(code);
+    let mut selectors: Vec<[u8; 4]> = vec![];
+    let mut delegates = false;
+
+    let mut index = 0;
+    while index < code.len() {
+        let opcode = code[index];
+
+        if opcode == PUSH4 && index + 5 <= code.len() {
+            let selector: [u8; 4] = code[index + 1..index + 5].try_into().unwrap_or_default();
+            selectors.push(selector);
+        }
+        if opcode == DELEGATECALL {
+            delegates = true;
+        }
+
+        // PUSH1..PUSH32 are followed by 1..32 bytes of data, not opcodes
+        index += match opcode {
+            PUSH1..=PUSH32 => (opcode - PUSH1) as usize + 2,
+            _ => 1,
+        };
+    }
+    selectors.sort();
+    selectors.dedup();
+
+    let mut matched: Vec<(Capability, &str)> = selectors
+        .iter()
+        .filter_map(|selector| RISK_CATALOGUE.get(selector).copied())
+        .collect();
+    matched.sort();
+
+    let mut capabilities: Vec<Capability> =
+        matched.iter().map(|(capability, _)| *capability).collect();
+    capabilities.dedup();
+
+    RiskReport {
+        capabilities,
+        signatures: matched
+            .iter()
+            .map(|(_, signature)| signature.to_string())
+            .collect(),
+        selectors_found: selectors.len(),
+        code_size,
+        delegates,
+    }
+}
+
+/// Cuts off the CBOR metadata solc appends, its hash bytes would be read as opcodes.
+/// The last two bytes are its length and it starts with a CBOR map.
+fn strip_metadata(code: &[u8]) -> &[u8] {
+    if code.len() < 2 {
+        return code;
+    }
+
+    let length = u16::from_be_bytes([code[code.len() - 2], code[code.len() - 1]]) as usize + 2;
+    match code.len().checked_sub(length) {
+        Some(start) if matches!(code[start], 0xa1..=0xa5) => &code[..start],
+        _ => code,
+    }
+}
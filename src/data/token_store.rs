@@ -0,0 +1,122 @@
+use crate::data::tokens::Erc20Token;
+use anyhow::{anyhow, Result};
+use ethers::types::{Address, Chain};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// directory the journals are kept in unless TOKEN_STORE_DIR says otherwise
+pub const DEFAULT_TOKEN_STORE_DIR: &str = "store";
+
+/// One line of a token journal, replaying them in order rebuilds the registry
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum JournalEntry {
+    Upsert { token: Box<Erc20Token> },
+    Remove { address: Address },
+}
+
+/// Append-only JSONL journal of every change to one chain's token registry
+pub struct TokenJournal {
+    writer: BufWriter<File>,
+}
+
+impl TokenJournal {
+    /// journal file of `chain` inside `dir`
+    pub fn path(dir: impl AsRef<Path>, chain: Chain) -> PathBuf {
+        dir.as_ref().join(format!("tokens-{}.jsonl", chain))
+    }
+
+    /// Replays the journal at `path` (if any), compacts it down to one upsert per token
+    /// and opens it for appending. Returns the journal and the tokens it held.
+    pub fn open(path: impl AsRef<Path>) -> Result<(Self, HashMap<Address, Erc20Token>)> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let tokens = if path.exists() {
+            replay_journal(path)?
+        } else {
+            HashMap::new()
+        };
+
+        // write the compacted journal next to the old one and swap it in, so a crash
+        // half way through leaves the old journal intact
+        let compacted_path = path.with_extension("jsonl.tmp");
+        {
+            let mut compacted = BufWriter::new(File::create(&compacted_path)?);
+            for token in tokens.values() {
+                write_entry(
+                    &mut compacted,
+                    &JournalEntry::Upsert {
+                        token: Box::new(token.clone()),
+                    },
+                )?;
+            }
+            compacted.flush()?;
+            compacted.get_ref().sync_all()?;
+        }
+        fs::rename(&compacted_path, path)?;
+
+        let file = OpenOptions::new().append(true).open(path)?;
+        let journal = Self {
+            writer: BufWriter::new(file),
+        };
+
+        Ok((journal, tokens))
+    }
+
+    pub fn append(&mut self, entry: &JournalEntry) -> Result<()> {
+        write_entry(&mut self.writer, entry)?;
+        // flush every line so a crash loses at most the change being written
+        self.writer.flush()?;
+
+        Ok(())
+    }
+}
+
+/// Rebuilds the registry from a journal. A torn last line, from a crash mid write, is
+/// skipped, a bad line anywhere else is an error.
+pub fn replay_journal(path: impl AsRef<Path>) -> Result<HashMap<Address, Erc20Token>> {
+    let lines: Vec<String> = BufReader::new(File::open(path)?)
+        .lines()
+        .collect::<Result<_, _>>()?;
+
+    let mut tokens = HashMap::<Address, Erc20Token>::new();
+    for (index, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let entry: JournalEntry = match serde_json::from_str(line) {
+            Ok(entry) => entry,
+            Err(error) if index == lines.len() - 1 => {
+                warn!("skipping torn last journal line => {}", error);
+                continue;
+            }
+            Err(error) => return Err(anyhow!("journal line {}: {}", index + 1, error)),
+        };
+
+        match entry {
+            JournalEntry::Upsert { token } => {
+                tokens.insert(token.address, *token);
+            }
+            JournalEntry::Remove { address } => {
+                tokens.remove(&address);
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn write_entry(writer: &mut impl Write, entry: &JournalEntry) -> Result<()> {
+    serde_json::to_writer(&mut *writer, entry)?;
+    writer.write_all(b"\n")?;
+
+    Ok(())
+}
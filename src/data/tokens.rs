@@ -1,17 +1,33 @@
-use super::token_data::{get_and_save_erc20_by_token_address, get_tokens, update_token};
+use super::lifecycle::{StateChange, TokenState};
+use super::token_data::TokenRegistry;
+use super::token_risk::RiskReport;
 use crate::swap::anvil_simlator::AnvilSimulator;
-use crate::swap::token_price::get_token_weth_liquidity;
-use crate::{data::token_data::remove_token, uniswap_v3_events::PoolCreatedEvent};
+use crate::swap::honeypot::simulate_round_trip;
+use crate::swap::token_price::get_token_quote_liquidity;
+use crate::swap::transfer_tax::TokenTaxes;
+use crate::uniswap_v2_events::PairCreatedEvent;
+use crate::uniswap_v3_events::PoolCreatedEvent;
+use anyhow::anyhow;
 use ethers::{
     abi::Address,
     core::types::U256,
     providers::{Provider, Ws},
 };
 use futures::lock::Mutex;
-use log::info;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
 
-#[derive(Clone, Default, Debug)]
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Dex {
+    #[default]
+    UniswapV3,
+    UniswapV2,
+}
+
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Erc20Token {
     pub name: String,
     pub symbol: String,
@@ -19,34 +35,126 @@ pub struct Erc20Token {
     pub fee: u32,
     pub address: Address,
     pub pool_address: Address,
-    pub is_tradable: bool,
+    pub dex: Dex,
     pub is_token_0: bool,
-    pub done_buying: bool,
-    pub amount_bought: U256,
-    pub time_of_purchase: u32,
+    /// token the pool pairs it with, see `QuoteToken`
+    pub quote_token: Address,
+    pub quote_decimals: u8,
+    pub total_supply: U256,
+    /// sqrtPriceX96 the v3 pool was initialized at, zero until it is
+    pub launch_sqrt_price_x96: U256,
+    pub launch_tick: i32,
+    pub state: TokenState,
+    /// every state the token entered, oldest first
+    pub history: Vec<StateChange>,
+    /// metadata fields the token did not return, see `TokenMetadata`
+    pub missing_metadata: Vec<String>,
+    /// taxes measured on the last test trades, none until the first
+    pub taxes: Option<TokenTaxes>,
+    /// what the token's code lets its owner do, none if the code could not be read
+    pub risk: Option<RiskReport>,
+}
+
+/// sells sent before a position is given up as stuck
+pub const MAX_SELL_ATTEMPTS: u32 = 3;
+
+impl Erc20Token {
+    /// Moves the token to `next` and records when, refusing moves the lifecycle does not allow
+    pub fn transition(&mut self, next: TokenState) -> anyhow::Result<()> {
+        if !self.state.can_become(&next) {
+            return Err(anyhow!(
+                "{} ({:?}) can't go from {} to {}",
+                self.name,
+                self.address,
+                self.state,
+                next
+            ));
+        }
+
+        self.history.push(StateChange::now(next.clone()));
+        self.state = next;
+
+        Ok(())
+    }
 }
 
 pub async fn add_validate_buy_new_token(
+    registry: &TokenRegistry,
     pool_created_event: &PoolCreatedEvent,
     client: &Arc<Provider<Ws>>,
     anvil: &Arc<AnvilSimulator>,
     current_time: u32,
 ) -> anyhow::Result<()> {
-    // TODO - VALIDATE TOKEN HERE - IF SCAM exit out
+    // SAVE TOKEN TO GLOBAL STATE
+    if let Some(token) = registry
+        .get_and_save_erc20_by_token_address(
+            &pool_created_event,
+            &anvil.strategy.current().launch_price_bounds,
+            client,
+        )
+        .await?
+    {
+        buy_token_if_liquid(registry, &token, client, anvil, current_time).await?;
+    }
+
+    Ok(())
+}
 
+pub async fn add_validate_buy_new_v2_token(
+    registry: &TokenRegistry,
+    pair_created_event: &PairCreatedEvent,
+    client: &Arc<Provider<Ws>>,
+    anvil: &Arc<AnvilSimulator>,
+    current_time: u32,
+) -> anyhow::Result<()> {
     // SAVE TOKEN TO GLOBAL STATE
-    if let Some(token) = get_and_save_erc20_by_token_address(&pool_created_event, client).await? {
-        // check liqudity
-        let token_liquidity = get_token_weth_liquidity(&token, client).await?;
+    if let Some(token) = registry
+        .get_and_save_v2_erc20_by_token_address(
+            pair_created_event,
+            &anvil.strategy.current().launch_price_bounds,
+            client,
+        )
+        .await?
+    {
+        buy_token_if_liquid(registry, &token, client, anvil, current_time).await?;
+    }
 
-        if token_liquidity > 0 {
-            info!(
-                "{} has immediate liquidity of {} and ready for trading",
-                token.name, token_liquidity
-            );
-            purchase_token_on_anvil(&token, anvil, current_time).await?;
-        } else {
-            info!("{} has no liquidity, cannot purchase yet!", token.name);
+    Ok(())
+}
+
+async fn buy_token_if_liquid(
+    registry: &TokenRegistry,
+    token: &Erc20Token,
+    client: &Arc<Provider<Ws>>,
+    anvil: &Arc<AnvilSimulator>,
+    current_time: u32,
+) -> anyhow::Result<()> {
+    // a pool for a token that is already further along changes nothing
+    if !matches!(
+        token.state,
+        TokenState::Discovered | TokenState::AwaitingLiquidity
+    ) {
+        return Ok(());
+    }
+
+    // check liqudity
+    let token_liquidity = get_token_quote_liquidity(token, client).await?;
+
+    if token_liquidity > 0 {
+        info!(
+            "{} has immediate liquidity of {} and ready for trading",
+            token.name, token_liquidity
+        );
+        let token = registry
+            .transition_token(token.address, TokenState::Validating)
+            .await?;
+        purchase_token_on_anvil(registry, &token, anvil, current_time).await?;
+    } else {
+        info!("{} has no liquidity, cannot purchase yet!", token.name);
+        if token.state == TokenState::Discovered {
+            registry
+                .transition_token(token.address, TokenState::AwaitingLiquidity)
+                .await?;
         }
     }
 
@@ -54,75 +162,205 @@ pub async fn add_validate_buy_new_token(
 }
 
 pub async fn buy_eligible_tokens_on_anvil(
+    registry: &TokenRegistry,
     anvil: &Arc<AnvilSimulator>,
     timestamp: u32,
 ) -> anyhow::Result<()> {
-    let tokens = get_tokens().await;
+    let tokens = registry
+        .get_tokens_in_state(|state| *state == TokenState::Validating)
+        .await;
 
     println!("finding tokens to buy");
-    for token in tokens.values() {
-        if !token.done_buying && token.is_tradable {
-            purchase_token_on_anvil(token, anvil, timestamp).await?;
-        }
+    for token in tokens.iter() {
+        purchase_token_on_anvil(registry, token, anvil, timestamp).await?;
     }
     println!("done with purchasing...");
     Ok(())
 }
 
 pub async fn sell_eligible_tokens_on_anvil(
+    registry: &TokenRegistry,
     anvil: &Arc<AnvilSimulator>,
     current_time: u32,
 ) -> anyhow::Result<()> {
-    let tokens = get_tokens().await;
-    let time_to_sell =
-        std::env::var("SELL_TOKEN_AFTER").expect("SELL_TOKEN_AFTER not found in .env");
-    let time_to_sell: u32 = time_to_sell.parse()?;
+    let time_to_sell = anvil.strategy.current().hold_time_secs;
+
+    // positions past their hold time, and sells that failed and are retried
+    let tokens = registry
+        .get_tokens_in_state(|state| match state {
+            TokenState::Bought { bought_at, .. } => {
+                current_time >= bought_at.saturating_add(time_to_sell)
+            }
+            TokenState::Selling { .. } => true,
+            _ => false,
+        })
+        .await;
 
     println!("finding tokens to sell");
-    for token in tokens.values() {
-        let sell_time = time_to_sell + token.time_of_purchase;
+    for token in tokens.iter() {
+        sell_token_on_anvil(registry, token, anvil).await?;
+    }
+
+    println!("done with selling...");
+    Ok(())
+}
+
+/// Checks every position loaded from the token store against what the anvil account
+/// actually holds, see `reconcile_position_balances`.
+pub async fn reconcile_positions(
+    registry: &TokenRegistry,
+    anvil: &Arc<AnvilSimulator>,
+) -> anyhow::Result<()> {
+    let mut balances = HashMap::new();
+    for token in registry.get_tokens_in_state(TokenState::is_open).await {
+        let balance = anvil.get_token_balance_by_address(token.address).await?;
+        balances.insert(token.address, balance);
+    }
+
+    reconcile_position_balances(registry, &balances).await
+}
+
+/// Brings open positions in line with `balances`, the anvil account's balance of each
+/// token. Positions that are held take the on-chain amount. A fresh fork holds nothing, so
+/// a missing balance doesn't mean the position is gone, it is kept and marked stuck
+/// with the amount it had.
+pub async fn reconcile_position_balances(
+    registry: &TokenRegistry,
+    balances: &HashMap<Address, U256>,
+) -> anyhow::Result<()> {
+    let positions = registry.get_tokens_in_state(TokenState::is_open).await;
+
+    for mut token in positions {
+        let amount = token.state.amount_held();
+        let balance = balances.get(&token.address).copied().unwrap_or_default();
 
-        if token.done_buying && current_time >= sell_time {
-            sell_token_on_anvil(token, anvil).await?;
+        if balance.is_zero() {
+            let reason = format!(
+                "{} not held by the anvil account after a restart, the fork was reset",
+                amount
+            );
+            warn!(
+                "position in {} ({:?}) => {}",
+                token.name, token.address, reason
+            );
+            registry
+                .transition_token(token.address, TokenState::Stuck { amount, reason })
+                .await?;
+        } else if balance != amount {
+            info!(
+                "position in {} reconciled from {} to {}",
+                token.name, amount, balance
+            );
+            if let TokenState::Bought { amount, .. } | TokenState::Selling { amount, .. } =
+                &mut token.state
+            {
+                *amount = balance;
+            }
+            registry.update_token(&token).await;
         }
     }
 
-    println!("done with selling...");
     Ok(())
 }
 
 pub async fn purchase_token_on_anvil(
+    registry: &TokenRegistry,
     token: &Erc20Token,
     anvil: &Arc<AnvilSimulator>,
     current_time: u32,
 ) -> anyhow::Result<()> {
+    // every buy path ends up here, a blacklisted token stays validating in case it is
+    // taken off the blacklist while running
+    if anvil.strategy.current().is_blacklisted(token.address) {
+        info!("{} is blacklisted, not buying", token.name);
+        return Ok(());
+    }
+
+    // every buy attempt is checked first, a token can turn into a honeypot after launch
+    let round_trip = match simulate_round_trip(token, anvil).await? {
+        Some(round_trip) => round_trip,
+        None => {
+            info!("{} test buy got no tokens, trying again later", token.name);
+            return Ok(());
+        }
+    };
+
+    // later trades size their slippage and sells by what was just measured
+    let token = registry
+        .record_taxes(token.address, round_trip.taxes)
+        .await?;
+
+    let max_loss_bps = anvil.strategy.current().max_round_trip_loss_bps;
+    if let Some(reason) = round_trip.rejection_reason(max_loss_bps) {
+        warn!(
+            "rejecting {} ({:?}) => {}",
+            token.name, token.address, reason
+        );
+        registry
+            .transition_token(token.address, TokenState::Rejected { reason })
+            .await?;
+        return Ok(());
+    }
+
     let token_balance = anvil.simulate_buying_token_for_weth(&token).await?;
 
     if token_balance > U256::from(0) {
-        let updated_token = Erc20Token {
-            is_tradable: true,
-            amount_bought: token_balance,
-            time_of_purchase: current_time,
-            done_buying: true,
-            ..token.clone()
+        let bought = TokenState::Bought {
+            amount: token_balance,
+            bought_at: current_time,
         };
-
-        update_token(&updated_token).await;
+        registry.transition_token(token.address, bought).await?;
         info!("token updated and saved");
     }
 
     Ok(())
 }
 
+/// Sends one sell for a bought token. A failed sell is retried on the next pass until
+/// MAX_SELL_ATTEMPTS, after that the position is marked stuck and left alone.
 pub async fn sell_token_on_anvil(
+    registry: &TokenRegistry,
     token: &Erc20Token,
     anvil: &Arc<AnvilSimulator>,
 ) -> anyhow::Result<()> {
-    let token_balance = anvil.simulate_selling_token_for_weth(&token).await?;
+    let (amount, attempts) = match token.state {
+        TokenState::Bought { amount, .. } => (amount, 1),
+        TokenState::Selling { amount, attempts } => (amount, attempts + 1),
+        _ => {
+            return Err(anyhow!(
+                "{} has no position to sell, it is {}",
+                token.name,
+                token.state
+            ))
+        }
+    };
+    let token = registry
+        .transition_token(token.address, TokenState::Selling { amount, attempts })
+        .await?;
+
+    let failure = match anvil.simulate_selling_token_for_weth(&token).await {
+        Ok(token_balance) if token_balance.is_zero() => {
+            registry
+                .transition_token(token.address, TokenState::Sold)
+                .await?;
+            info!("token {} sold!", token.name);
+            return Ok(());
+        }
+        Ok(token_balance) => format!("{} tokens still held", token_balance),
+        Err(error) => error.to_string(),
+    };
 
-    if token_balance == U256::from(0) {
-        let token = remove_token(token.address).await.unwrap();
-        info!("token {} sold and removed!", token.name);
+    if attempts >= MAX_SELL_ATTEMPTS {
+        let reason = format!("{} sells failed, last => {}", attempts, failure);
+        error!("giving up on selling {} => {}", token.name, reason);
+        registry
+            .transition_token(token.address, TokenState::Stuck { amount, reason })
+            .await?;
+    } else {
+        warn!(
+            "selling {} failed ({}/{}) => {}",
+            token.name, attempts, MAX_SELL_ATTEMPTS, failure
+        );
     }
 
     Ok(())
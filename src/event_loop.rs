@@ -0,0 +1,626 @@
+use crate::backfill::get_logs_in_pages;
+use crate::data::contracts::{current_chain, CONTRACT};
+use crate::data::lifecycle::TokenState;
+use crate::data::token_data::TokenRegistry;
+use crate::data::tokens::{
+    add_validate_buy_new_token, add_validate_buy_new_v2_token, buy_eligible_tokens_on_anvil,
+    sell_eligible_tokens_on_anvil, Dex,
+};
+use crate::event_source::{EventSource, LiveEventSource, ReplayEventSource, SessionRecorder};
+use crate::mempool::{
+    decode_pending_launches, pre_register_pending_launch, subscribe_to_launch_transactions,
+    LaunchContracts,
+};
+use crate::reorg::{track_block, BlockWindow, Reorg};
+use crate::swap::anvil_simlator::AnvilSimulator;
+use crate::swap::launch_price::{launch_rejection_reason, LaunchPriceBounds};
+use crate::uniswap_v3_events::UniswapEvent;
+use crate::{uniswap_v2_events, uniswap_v3_events};
+use anyhow::Result;
+use ethers::{
+    core::types::{Address, Block, Log, Transaction, TxHash},
+    providers::{Middleware, Provider, Ws},
+};
+use futures::{lock::Mutex, stream, StreamExt};
+use log::{error, info, warn};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// no event for this long means the subscription has silently died
+pub const STALL_TIMEOUT: Duration = Duration::from_secs(60);
+pub const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+pub const MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// Mint logs flip tokens to tradable, liquidity is only polled every this many blocks
+pub const TRADABILITY_POLL_INTERVAL: u64 = 25;
+
+#[allow(clippy::large_enum_variant)]
+pub enum Event {
+    Block(Block<TxHash>),
+    Log(Log),
+    Reorg(Reorg),
+    PendingTransaction(Transaction),
+}
+
+/// State that has to survive a reconnect
+pub struct EventLoopState {
+    pub registry: TokenRegistry,
+    pub last_block_timestamp: Arc<Mutex<u32>>,
+    pub last_processed_block: Arc<Mutex<u64>>,
+    pub recent_blocks: Arc<Mutex<BlockWindow>>,
+    pub recorder: Option<Arc<Mutex<SessionRecorder>>>,
+}
+
+impl EventLoopState {
+    pub fn new(registry: TokenRegistry, block_timestamp: u32, block_number: u64) -> Self {
+        Self {
+            registry,
+            last_block_timestamp: Arc::new(Mutex::new(block_timestamp)),
+            last_processed_block: Arc::new(Mutex::new(block_number)),
+            recent_blocks: Arc::new(Mutex::new(BlockWindow::default())),
+            recorder: None,
+        }
+    }
+
+    /// records every dispatched event so the session can be replayed later
+    pub fn with_recorder(mut self, recorder: SessionRecorder) -> Self {
+        self.recorder = Some(Arc::new(Mutex::new(recorder)));
+        self
+    }
+}
+
+/// Connects, subscribes and processes events forever. Whenever the stream ends, stalls
+/// or the connection fails it reconnects with exponential backoff and fills the gap
+/// between the last processed block and the new head before resuming.
+pub async fn run_event_loop_with_reconnect(
+    ws_url: &str,
+    anvil: &Arc<AnvilSimulator>,
+    state: &EventLoopState,
+) -> Result<()> {
+    let chain = current_chain()?;
+    let mut attempt: u32 = 0;
+
+    loop {
+        match Provider::<Ws>::connect(ws_url).await {
+            Ok(provider) => {
+                attempt = 0;
+                let client = Arc::new(provider);
+                info!("Connected to {}", chain);
+
+                match run_event_loop(&client, anvil, state).await {
+                    Ok(()) => warn!("event stream ended, reconnecting..."),
+                    Err(error) => error!("event loop stopped => {}, reconnecting...", error),
+                }
+            }
+            Err(error) => error!("could not connect to {} => {}", chain, error),
+        }
+
+        let delay = backoff_delay(attempt);
+        attempt = attempt.saturating_add(1);
+        warn!("waiting {:?} before reconnecting", delay);
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Subscribes to all event streams, fills any gap since the last processed block and
+/// then processes events until the stream ends (Ok) or stalls (Err)
+pub async fn run_event_loop(
+    client: &Arc<Provider<Ws>>,
+    anvil: &Arc<AnvilSimulator>,
+    state: &EventLoopState,
+) -> Result<()> {
+    // subscribe before filling the gap so nothing lands between the two,
+    // duplicates are harmless since tokens are only saved once
+    let mut source = LiveEventSource::new(subscribe_to_events(client).await?, STALL_TIMEOUT);
+    let mut watched_pools = WatchedPools::default();
+    watch_pools(&mut source, &mut watched_pools, client, &state.registry).await?;
+
+    if let Err(error) = fill_gap(client, anvil, state).await {
+        error!("could not fill gap since last processed block => {}", error);
+    }
+
+    // any event can add or drop a pool waiting for liquidity
+    while let Some(event) = source.next_event().await? {
+        dispatch_event(Ok(event), client, anvil, state).await;
+        watch_pools(&mut source, &mut watched_pools, client, &state.registry).await?;
+    }
+
+    Ok(())
+}
+
+/// Pools and pairs whose Mint (and v3 Initialize) logs are subscribed to, the ones of
+/// tokens still waiting for liquidity
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WatchedPools {
+    pub v3: Vec<Address>,
+    pub v2: Vec<Address>,
+}
+
+impl WatchedPools {
+    pub async fn of(registry: &TokenRegistry) -> Self {
+        Self {
+            v3: registry.get_pools_awaiting_liquidity(Dex::UniswapV3).await,
+            v2: registry.get_pools_awaiting_liquidity(Dex::UniswapV2).await,
+        }
+    }
+}
+
+/// Resubscribes to pool events when the pools waiting for liquidity changed. The new
+/// subscription is in place before the old one is dropped, so no log falls in between.
+async fn watch_pools<'a>(
+    source: &mut LiveEventSource<'a>,
+    watched_pools: &mut WatchedPools,
+    client: &'a Arc<Provider<Ws>>,
+    registry: &TokenRegistry,
+) -> Result<()> {
+    let pools = WatchedPools::of(registry).await;
+    if pools == *watched_pools {
+        return Ok(());
+    }
+
+    source.set_pool_stream(subscribe_to_pool_events(client, &pools).await?);
+    info!(
+        "Subscribed to Mint logs of {} v3 pools and {} v2 pairs",
+        pools.v3.len(),
+        pools.v2.len()
+    );
+    *watched_pools = pools;
+
+    Ok(())
+}
+
+/// Subscribes to the Mint and Initialize logs of `pools`. Filters without an address match
+/// every pool on the chain, so nothing is subscribed for an empty list.
+pub async fn subscribe_to_pool_events<'a>(
+    client: &'a Arc<Provider<Ws>>,
+    pools: &WatchedPools,
+) -> Result<stream::BoxStream<'a, Result<Event>>> {
+    let mut filters = vec![];
+    if !pools.v3.is_empty() {
+        filters.push(uniswap_v3_events::set_mint_filter(pools.v3.clone()));
+        filters.push(uniswap_v3_events::set_initialize_filter(pools.v3.clone()));
+    }
+    if !pools.v2.is_empty() {
+        filters.push(uniswap_v2_events::set_mint_filter(pools.v2.clone()));
+    }
+
+    if filters.is_empty() {
+        return Ok(stream::pending().boxed());
+    }
+
+    let mut streams = vec![];
+    for filter in &filters {
+        streams.push(
+            client
+                .subscribe_logs(filter)
+                .await?
+                .map(|log| Ok(Event::Log(log)))
+                .boxed(),
+        );
+    }
+
+    Ok(stream::select_all(streams).boxed())
+}
+
+/// Feeds every event of `source` through `dispatch_event` until it is exhausted
+pub async fn run_event_source<S: EventSource>(
+    source: &mut S,
+    client: &Arc<Provider<Ws>>,
+    anvil: &Arc<AnvilSimulator>,
+    state: &EventLoopState,
+) -> Result<()> {
+    while let Some(event) = source.next_event().await? {
+        dispatch_event(Ok(event), client, anvil, state).await;
+    }
+
+    Ok(())
+}
+
+/// Replays a session recorded with `SessionRecorder` through the same handlers as the
+/// live loop. `client` should point at a fork pinned to the block the session started at.
+pub async fn replay_session(
+    path: &str,
+    client: &Arc<Provider<Ws>>,
+    anvil: &Arc<AnvilSimulator>,
+    state: &EventLoopState,
+) -> Result<()> {
+    info!("replaying session from {}", path);
+    let mut source = ReplayEventSource::open(path).await?;
+
+    run_event_source(&mut source, client, anvil, state).await?;
+
+    info!("replay of {} finished", path);
+    Ok(())
+}
+
+pub async fn subscribe_to_events(
+    client: &Arc<Provider<Ws>>,
+) -> Result<stream::BoxStream<'_, Result<Event>>> {
+    let event_filter = uniswap_v3_events::set_signature_filter()?;
+    // Create multiple subscription streams.
+    let log_stream: stream::BoxStream<'_, Result<Event>> = client
+        .subscribe_logs(&event_filter)
+        .await?
+        .map(|log| Ok(Event::Log(log)))
+        .boxed();
+
+    info!("Subscribed to uniswap v3 PoolCreated logs");
+
+    let block_stream: stream::BoxStream<'_, Result<Event>> = client
+        .subscribe_blocks()
+        .await?
+        .map(|block| Ok(Event::Block(block)))
+        .boxed();
+
+    info!("Subscribed to new blocks");
+
+    // Mint and Initialize logs only of tracked pools, see subscribe_to_pool_events
+    let mut streams = vec![log_stream, block_stream];
+
+    // chains without a v2 deployment in contracts.toml only snipe v3 pools
+    if CONTRACT.get_address().has_uniswap_v2() {
+        let pair_filter = uniswap_v2_events::set_pair_created_filter()?;
+        streams.push(
+            client
+                .subscribe_logs(&pair_filter)
+                .await?
+                .map(|log| Ok(Event::Log(log)))
+                .boxed(),
+        );
+
+        info!("Subscribed to uniswap v2 PairCreated logs");
+    }
+
+    // every pending tx has to be fetched, so only watch the mempool when asked to
+    if std::env::var("WATCH_MEMPOOL").is_ok_and(|watch| watch == "true") {
+        streams.push(subscribe_to_launch_transactions(client).await?);
+        info!("Subscribed to pending launch transactions");
+    }
+
+    // Merge the streams into a single stream.
+    Ok(stream::select_all(streams).boxed())
+}
+
+/// Replays pool / pair created logs and blocks in `(last processed block, head]`
+/// through `handle_event`, in block order
+pub async fn fill_gap(
+    client: &Arc<Provider<Ws>>,
+    anvil: &Arc<AnvilSimulator>,
+    state: &EventLoopState,
+) -> Result<()> {
+    let last_processed_block = *state.last_processed_block.lock().await;
+    let head = client.get_block_number().await?.as_u64();
+
+    if head <= last_processed_block {
+        return Ok(());
+    }
+
+    info!(
+        "filling gap from block {} to {}",
+        last_processed_block + 1,
+        head
+    );
+    process_block_range(client, anvil, state, last_processed_block + 1, head).await
+}
+
+/// Runs the pool / pair created logs and blocks of `from_block..=to_block` through
+/// `dispatch_event`, in block order, then polls liquidity since mint logs are not fetched
+async fn process_block_range(
+    client: &Arc<Provider<Ws>>,
+    anvil: &Arc<AnvilSimulator>,
+    state: &EventLoopState,
+    from_block: u64,
+    to_block: u64,
+) -> Result<()> {
+    if to_block < from_block {
+        return Ok(());
+    }
+
+    let pool_filter = uniswap_v3_events::set_signature_filter()?;
+    let mut logs = get_logs_in_pages(client, &pool_filter, from_block, to_block).await?;
+    if CONTRACT.get_address().has_uniswap_v2() {
+        let pair_filter = uniswap_v2_events::set_pair_created_filter()?;
+        logs.extend(get_logs_in_pages(client, &pair_filter, from_block, to_block).await?);
+    }
+    logs.sort_by_key(|log| (log.block_number, log.log_index));
+
+    let mut logs = logs.into_iter().peekable();
+    for block_number in from_block..=to_block {
+        while let Some(log) =
+            logs.next_if(|log| log.block_number.map(|n| n.as_u64()) <= Some(block_number))
+        {
+            dispatch_event(Ok(Event::Log(log)), client, anvil, state).await;
+        }
+
+        match client.get_block(block_number).await? {
+            Some(block) => dispatch_event(Ok(Event::Block(block)), client, anvil, state).await,
+            None => warn!("block {} missing while processing blocks", block_number),
+        }
+    }
+
+    // mint logs are not fetched for the range, poll liquidity once instead
+    state
+        .registry
+        .check_all_tokens_and_update_if_are_tradable(client)
+        .await?;
+
+    Ok(())
+}
+
+/// Checks every block against the recent block window first, so a reorg is handled
+/// as its own event before the block that caused it
+pub async fn dispatch_event(
+    event: Result<Event>,
+    client: &Arc<Provider<Ws>>,
+    anvil: &Arc<AnvilSimulator>,
+    state: &EventLoopState,
+) {
+    if let (Ok(event), Some(recorder)) = (&event, &state.recorder) {
+        if let Err(error) = recorder.lock().await.record(event) {
+            error!("could not record event => {}", error);
+        }
+    }
+
+    if let Ok(Event::Block(block)) = &event {
+        let mut recent_blocks = state.recent_blocks.lock().await;
+
+        match track_block(client, &mut recent_blocks, block).await {
+            Ok(Some(reorg)) => {
+                drop(recent_blocks);
+                let (common_ancestor, new_head) = (reorg.common_ancestor, reorg.new_head);
+                handle_event(Ok(Event::Reorg(reorg)), client, anvil, state).await;
+
+                // blocks of the new chain below its head were never processed, the head
+                // itself is handled below. They are in the block window already, so
+                // dispatching them again can't report the same reorg.
+                let reprocess = process_block_range(
+                    client,
+                    anvil,
+                    state,
+                    common_ancestor + 1,
+                    new_head.saturating_sub(1),
+                );
+                if let Err(error) = Box::pin(reprocess).await {
+                    error!("could not re-process blocks after reorg => {}", error);
+                }
+            }
+            Ok(None) => {}
+            Err(error) => error!("could not check block for reorg => {}", error),
+        }
+    }
+
+    handle_event(event, client, anvil, state).await;
+}
+
+pub async fn handle_event(
+    event: Result<Event>,
+    client: &Arc<Provider<Ws>>,
+    anvil: &Arc<AnvilSimulator>,
+    state: &EventLoopState,
+) {
+    match event {
+        Ok(Event::Log(log))
+            if uniswap_v3_events::is_mint_log(&log) || uniswap_v2_events::is_mint_log(&log) =>
+        {
+            handle_mint_log(&log, &state.registry).await
+        }
+        Ok(Event::Log(log)) if uniswap_v3_events::is_initialize_log(&log) => {
+            let bounds = anvil.strategy.current().launch_price_bounds;
+            handle_initialize_log(&log, &bounds, client, &state.registry).await
+        }
+        Ok(Event::Log(log)) if log.removed == Some(true) => {
+            handle_removed_log(&log, &state.registry).await
+        }
+        Ok(Event::Log(log)) if uniswap_v2_events::is_pair_created_log(&log) => {
+            match uniswap_v2_events::decode_paircreated_event(&log) {
+                Ok(pair_created_event) => {
+                    info!("pair created event {:#?}", pair_created_event);
+                    let last_time = *state.last_block_timestamp.lock().await;
+
+                    if let Err(error) = add_validate_buy_new_v2_token(
+                        &state.registry,
+                        &pair_created_event,
+                        client,
+                        anvil,
+                        last_time,
+                    )
+                    .await
+                    {
+                        warn!("Could not run add_validate_buy_new_v2_token => {}", error);
+                    }
+                }
+                Err(error) => error!("error extracting pair created event => {}", error),
+            }
+        }
+        Ok(Event::Log(log)) => match uniswap_v3_events::decode_poolcreated_event(&log) {
+            Ok(pool_created_event) => {
+                info!("pool created event {:#?}", pool_created_event);
+                let last_time = *state.last_block_timestamp.lock().await;
+
+                if let Err(error) = add_validate_buy_new_token(
+                    &state.registry,
+                    &pool_created_event,
+                    client,
+                    anvil,
+                    last_time,
+                )
+                .await
+                {
+                    warn!("Could not run add_validate_buy_new_token => {}", error);
+                }
+            }
+            Err(error) => error!("error extracting pool created event => {}", error),
+        },
+        Ok(Event::Block(block)) => {
+            info!("NEW BLOCK ===> {}", block.timestamp);
+            let current_block_timestamp = block.timestamp.as_u32();
+            *state.last_block_timestamp.lock().await = current_block_timestamp;
+
+            // fallback in case a Mint log was missed
+            if block
+                .number
+                .is_some_and(|number| number.as_u64() % TRADABILITY_POLL_INTERVAL == 0)
+            {
+                if let Err(error) = state
+                    .registry
+                    .check_all_tokens_and_update_if_are_tradable(client)
+                    .await
+                {
+                    error!("could not check token tradability => {}", error);
+                }
+            }
+
+            if let Err(error) =
+                buy_eligible_tokens_on_anvil(&state.registry, anvil, current_block_timestamp).await
+            {
+                error!("error running buy_eligible_tokens_on_anvil => {}", error);
+            }
+
+            if let Err(error) =
+                sell_eligible_tokens_on_anvil(&state.registry, anvil, current_block_timestamp).await
+            {
+                error!("error running sell_eligible_tokens_on_anvil => {}", error);
+            }
+
+            if let Some(block_number) = block.number {
+                let mut last_processed_block = state.last_processed_block.lock().await;
+                *last_processed_block = (*last_processed_block).max(block_number.as_u64());
+            }
+        }
+        Ok(Event::PendingTransaction(tx)) => {
+            let contracts = LaunchContracts::from_config();
+            let bounds = anvil.strategy.current().launch_price_bounds;
+
+            for launch in decode_pending_launches(&tx, &contracts) {
+                info!("pending launch in tx {:?} => {:#?}", tx.hash, launch);
+
+                if let Err(error) =
+                    pre_register_pending_launch(&state.registry, &launch, &bounds, client).await
+                {
+                    warn!("could not pre-register pending launch => {}", error);
+                }
+            }
+        }
+        Ok(Event::Reorg(reorg)) => {
+            warn!(
+                "REORG of depth {} detected at block {}, common ancestor {}",
+                reorg.depth(),
+                reorg.new_head,
+                reorg.common_ancestor
+            );
+
+            // blocks after the common ancestor have to be processed again, and
+            // their timestamps no longer count
+            {
+                let mut last_processed_block = state.last_processed_block.lock().await;
+                *last_processed_block = (*last_processed_block).min(reorg.common_ancestor);
+            }
+
+            match client.get_block(reorg.common_ancestor).await {
+                Ok(Some(ancestor)) => {
+                    *state.last_block_timestamp.lock().await = ancestor.timestamp.as_u32();
+                }
+                Ok(None) => error!("common ancestor {} not found", reorg.common_ancestor),
+                Err(error) => error!(
+                    "could not fetch common ancestor {} => {}",
+                    reorg.common_ancestor, error
+                ),
+            }
+        }
+        Err(e) => error!("Error: {:?}", e),
+    }
+}
+
+/// Marks the token of a tracked pool / pair tradable as soon as liquidity is minted into it
+async fn handle_mint_log(log: &Log, registry: &TokenRegistry) {
+    // a reorged out mint usually lands again on the new chain, so it is ignored
+    if log.removed == Some(true) {
+        return;
+    }
+
+    if let Some(token) = registry
+        .mark_token_tradable_by_pool_address(log.address)
+        .await
+    {
+        info!(
+            "liquidity added to {:?}, {} is now tradable",
+            log.address, token.name
+        );
+    }
+}
+
+/// Records the launch price of a tracked pool and drops the token if it is out of bounds
+async fn handle_initialize_log(
+    log: &Log,
+    bounds: &LaunchPriceBounds,
+    client: &Arc<Provider<Ws>>,
+    registry: &TokenRegistry,
+) {
+    if log.removed == Some(true) {
+        return;
+    }
+
+    let (pool, event) = match uniswap_v3_events::decode_uniswap_event(log) {
+        Ok(UniswapEvent::Initialize { pool, event }) => (pool, event),
+        Ok(other) => return error!("expected Initialize event, found {}", other.name()),
+        Err(error) => return error!("error extracting initialize event => {}", error),
+    };
+
+    let token = match registry
+        .record_launch_price(pool, event.sqrt_price_x96, event.tick)
+        .await
+    {
+        Some(token) => token,
+        None => return,
+    };
+    info!(
+        "{} launched at sqrtPriceX96 {} (tick {})",
+        token.name, event.sqrt_price_x96, event.tick
+    );
+
+    match launch_rejection_reason(&token, bounds, client).await {
+        Ok(Some(reason)) => {
+            warn!(
+                "rejecting {} ({:?}) => {}",
+                token.name, token.address, reason
+            );
+            if let Err(error) = registry
+                .transition_token(token.address, TokenState::Rejected { reason })
+                .await
+            {
+                error!("could not reject token => {}", error);
+            }
+        }
+        Ok(None) => {}
+        Err(error) => error!("could not check launch price => {}", error),
+    }
+}
+
+/// Undoes registry entries created by a pool / pair created log that is no longer canonical
+async fn handle_removed_log(log: &Log, registry: &TokenRegistry) {
+    let pool_address = if uniswap_v2_events::is_pair_created_log(log) {
+        uniswap_v2_events::decode_paircreated_event(log).map(|event| event.pair)
+    } else {
+        uniswap_v3_events::decode_poolcreated_event(log).map(|event| event.pool)
+    };
+
+    match pool_address {
+        Ok(pool_address) => match registry.remove_token_by_pool_address(pool_address).await {
+            Some(token) if !token.state.amount_held().is_zero() => warn!(
+                "pool {:?} was reorged out, kept {} as {}",
+                pool_address, token.name, token.state
+            ),
+            Some(token) => warn!(
+                "pool {:?} was reorged out, removed {} from tracked tokens",
+                pool_address, token.name
+            ),
+            None => {}
+        },
+        Err(error) => error!("error extracting removed log => {}", error),
+    }
+}
+
+pub fn backoff_delay(attempt: u32) -> Duration {
+    INITIAL_BACKOFF
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_BACKOFF)
+}
@@ -0,0 +1,149 @@
+use crate::event_loop::Event;
+use anyhow::{anyhow, Result};
+use ethers::types::{Block, Log, Transaction, TxHash};
+use futures::{stream, Future, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader, Lines};
+
+/// Anything that yields the events the event loop handles, live or recorded
+pub trait EventSource {
+    /// Ok(None) once the source is exhausted, Err if it failed and should be restarted
+    fn next_event(&mut self) -> impl Future<Output = Result<Option<Event>>> + Send;
+}
+
+/// Merged websocket subscriptions, fails if nothing arrives within `stall_timeout`.
+/// The pool subscription is kept apart so it can be replaced as pools come and go.
+pub struct LiveEventSource<'a> {
+    stream: stream::BoxStream<'a, Result<Event>>,
+    pool_stream: stream::BoxStream<'a, Result<Event>>,
+    stall_timeout: Duration,
+}
+
+impl<'a> LiveEventSource<'a> {
+    pub fn new(stream: stream::BoxStream<'a, Result<Event>>, stall_timeout: Duration) -> Self {
+        Self {
+            stream,
+            pool_stream: stream::pending().boxed(),
+            stall_timeout,
+        }
+    }
+
+    /// swaps in a new pool subscription, the old one is unsubscribed when dropped
+    pub fn set_pool_stream(&mut self, pool_stream: stream::BoxStream<'a, Result<Event>>) {
+        self.pool_stream = pool_stream;
+    }
+}
+
+impl EventSource for LiveEventSource<'_> {
+    async fn next_event(&mut self) -> Result<Option<Event>> {
+        // the source ends with the main stream, an ended pool stream is just skipped
+        let next = async {
+            tokio::select! {
+                event = self.stream.next() => event,
+                Some(event) = self.pool_stream.next() => Some(event),
+            }
+        };
+
+        match tokio::time::timeout(self.stall_timeout, next).await {
+            Ok(Some(event)) => event.map(Some),
+            Ok(None) => Ok(None),
+            Err(_) => Err(anyhow!("no events received for {:?}", self.stall_timeout)),
+        }
+    }
+}
+
+/// One line of a recorded session file
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)]
+pub enum RecordedEvent {
+    Block(Block<TxHash>),
+    Log(Log),
+    PendingTransaction(Transaction),
+}
+
+impl RecordedEvent {
+    /// reorgs are not recorded, replaying the blocks detects them again
+    pub fn from_event(event: &Event) -> Option<Self> {
+        match event {
+            Event::Block(block) => Some(RecordedEvent::Block(block.clone())),
+            Event::Log(log) => Some(RecordedEvent::Log(log.clone())),
+            Event::PendingTransaction(tx) => Some(RecordedEvent::PendingTransaction(tx.clone())),
+            Event::Reorg(_) => None,
+        }
+    }
+
+    pub fn into_event(self) -> Event {
+        match self {
+            RecordedEvent::Block(block) => Event::Block(block),
+            RecordedEvent::Log(log) => Event::Log(log),
+            RecordedEvent::PendingTransaction(tx) => Event::PendingTransaction(tx),
+        }
+    }
+}
+
+/// Appends every event it is given to a JSONL file
+pub struct SessionRecorder {
+    writer: BufWriter<File>,
+}
+
+impl SessionRecorder {
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        Ok(Self {
+            writer: BufWriter::new(file),
+        })
+    }
+
+    pub fn record(&mut self, event: &Event) -> Result<()> {
+        if let Some(recorded_event) = RecordedEvent::from_event(event) {
+            serde_json::to_writer(&mut self.writer, &recorded_event)?;
+            self.writer.write_all(b"\n")?;
+            // flush every line so a crash still leaves a usable recording
+            self.writer.flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads events back from a file written by `SessionRecorder`
+pub struct ReplayEventSource {
+    lines: Lines<BufReader<tokio::fs::File>>,
+    line_number: usize,
+}
+
+impl ReplayEventSource {
+    pub async fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let file = tokio::fs::File::open(path).await?;
+
+        Ok(Self {
+            lines: BufReader::new(file).lines(),
+            line_number: 0,
+        })
+    }
+}
+
+impl EventSource for ReplayEventSource {
+    async fn next_event(&mut self) -> Result<Option<Event>> {
+        while let Some(line) = self.lines.next_line().await? {
+            self.line_number += 1;
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let recorded_event: RecordedEvent = serde_json::from_str(&line)
+                .map_err(|error| anyhow!("line {}: {}", self.line_number, error))?;
+
+            return Ok(Some(recorded_event.into_event()));
+        }
+
+        Ok(None)
+    }
+}
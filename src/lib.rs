@@ -1,8 +1,10 @@
 pub mod abi {
     pub mod erc20;
+    pub mod multicall3;
     pub mod uniswap_factory_v2;
     pub mod uniswap_pair;
     pub mod uniswap_pool;
+    pub mod uniswap_position_manager;
     pub mod uniswap_quoter;
     pub mod uniswap_router_v2;
     pub mod uniswap_v3_factory;
@@ -11,18 +13,36 @@ pub mod abi {
 
 pub mod utils {
     pub mod logging;
+    pub mod secrets;
     pub mod type_conversion;
 }
 
 pub mod data {
+    pub mod config_check;
+    pub mod config_watch;
     pub mod contracts;
+    pub mod lifecycle;
     pub mod token_data;
+    pub mod token_metadata;
+    pub mod token_risk;
+    pub mod token_store;
     pub mod tokens;
 }
 
+pub mod backfill;
+pub mod event_loop;
+pub mod event_source;
+pub mod mempool;
+pub mod multicall;
+pub mod reorg;
+pub mod strategy;
+pub mod uniswap_v2_events;
 pub mod uniswap_v3_events;
 
 pub mod swap {
     pub mod anvil_simlator;
+    pub mod honeypot;
+    pub mod launch_price;
     pub mod token_price;
+    pub mod transfer_tax;
 }
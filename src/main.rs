@@ -1,48 +1,112 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use dotenv::dotenv;
 use ethers::{
-    core::types::{Log, TxHash},
     providers::{Middleware, Provider, Ws},
-    types::BlockNumber,
+    types::{BlockNumber, Chain},
 };
-use futures::{lock::Mutex, stream, StreamExt};
-use log::{error, info, warn};
+use futures::future::join_all;
+use log::{error, info};
 use snipper::{
-    data::{
-        contracts::CHAIN,
-        token_data::check_all_tokens_and_update_if_are_tradable,
-        tokens::{add_validate_buy_new_token, sell_eligible_tokens_on_anvil},
+    backfill,
+    data::config_check::{config_check, ensure_chain_id},
+    data::config_watch::watch_config,
+    data::contracts::{
+        load_contracts, parse_supported_chain, select_chain, supported_chains, with_chain,
+        CONTRACT, DEFAULT_CHAIN,
     },
+    data::token_data::TokenRegistry,
+    data::token_store::DEFAULT_TOKEN_STORE_DIR,
+    data::tokens::reconcile_positions,
+    event_loop::{replay_session, run_event_loop_with_reconnect, EventLoopState},
+    event_source::SessionRecorder,
+    strategy::{load_strategy, SharedStrategy},
     swap::anvil_simlator::AnvilSimulator,
     utils::logging::setup_logger,
 };
-use snipper::{
-    data::{contracts::CONTRACT, tokens::buy_eligible_tokens_on_anvil},
-    uniswap_v3_events,
-};
 use std::sync::Arc;
 
-enum Event {
-    Block(ethers::types::Block<TxHash>),
-    Log(Log),
-    // PendingTransactions(TxHash),
-}
-
 #[tokio::main]
 async fn main() -> Result<()> {
     // initiate logger and environment variables
     dotenv().ok();
+    // report every problem in contracts.toml up front instead of panicking later
+    load_contracts()?;
+    let chains = chains_to_run()?;
+    let strategy = load_strategy(flag_argument("--profile", "STRATEGY_PROFILE").as_deref())?;
+    if let [chain] = chains[..] {
+        select_chain(chain.as_ref())?;
+    }
     setup_logger().expect("Failed to initialize logger.");
-    let ws_url = CONTRACT.get_address().ws_url.clone();
+
+    // PRINT THE RESOLVED CONFIG AND CHECK THE RPCS INSTEAD OF RUNNING
+    if config_check_requested() {
+        return config_check(&chains, &strategy).await;
+    }
+
+    // REPLAY A RECORDED SESSION INSTEAD OF LISTENING TO THE LIVE CHAIN
+    if let Ok(session_path) = std::env::var("REPLAY_SESSION") {
+        if chains.len() > 1 {
+            return Err(anyhow!(
+                "REPLAY_SESSION replays a single chain, pick one with --chain"
+            ));
+        }
+        let ws_url = CONTRACT.get_address().ws_url.to_string();
+        return replay(&session_path, &ws_url, SharedStrategy::new(strategy)).await;
+    }
+
+    // strategy.toml edits apply to every chain while running
+    let strategy = SharedStrategy::new(strategy);
+    // the watcher reloads config for every chain, it only runs inside one when there is one
+    let watcher = watch_config(strategy.clone());
+    match chains[..] {
+        [chain] => tokio::spawn(with_chain(chain, watcher)),
+        _ => tokio::spawn(watcher),
+    };
+
+    // every chain gets its own task, provider, anvil fork, token registry and event loop
+    let multi_chain = chains.len() > 1;
+    let runs = chains.iter().map(|&chain| {
+        let run = run_chain(chain, multi_chain, strategy.clone());
+        tokio::spawn(with_chain(chain, run))
+    });
+    let results = join_all(runs).await;
+
+    let mut stopped_chains = 0;
+    for (chain, result) in chains.iter().zip(results) {
+        match result {
+            Ok(Ok(())) => continue,
+            Ok(Err(error)) => error!("{} stopped => {}", chain, error),
+            Err(error) => error!("{} panicked => {}", chain, error),
+        }
+        stopped_chains += 1;
+    }
+
+    if stopped_chains == chains.len() {
+        return Err(anyhow!("every chain stopped"));
+    }
+
+    Ok(())
+}
+
+/// Sets up and runs the live event loop of the current chain
+async fn run_chain(chain: Chain, multi_chain: bool, strategy: SharedStrategy) -> Result<()> {
+    info!(
+        "running on {} with strategy {}",
+        chain,
+        strategy.current().profile
+    );
+    let ws_url = CONTRACT.get_address().ws_url.to_string();
+
     // setup provider
 
     let provider = Provider::<Ws>::connect(ws_url.clone()).await?;
     let client = Arc::new(provider);
-    info!("Connected to {:#?}", CHAIN);
+    ensure_chain_id(chain, "ws_url", client.get_chainid().await?)?;
+    info!("Connected to {}", chain);
 
     // creating anvil mainnet fork for testing
     info!("Connecting to Anvil...");
-    let anvil = AnvilSimulator::new(&ws_url).await?;
+    let anvil = AnvilSimulator::new(&ws_url, strategy).await?;
     let anvil = Arc::new(anvil);
     info!("Anvil connected!");
 
@@ -50,90 +114,124 @@ async fn main() -> Result<()> {
     let initial_block = client.get_block(BlockNumber::Latest).await?.unwrap();
     let last_block_timestamp = initial_block.timestamp.as_u32();
     info!("initial block timestamp => {}", last_block_timestamp);
-    let last_block_timestamp = Arc::new(Mutex::new(last_block_timestamp));
-
-    let event_filter = uniswap_v3_events::set_signature_filter()?;
-    // Create multiple subscription streams.
-    let log_stream: stream::BoxStream<'_, Result<Event>> = client
-        .subscribe_logs(&event_filter)
-        .await?
-        .map(|log| Ok(Event::Log(log)))
-        .boxed();
-
-    info!("Subscribed to aave v3 logs");
-
-    // let tx_stream: stream::BoxStream<'_, Result<Event>> = client
-    //     .subscribe_pending_txs()
-    //     .await?
-    //     .map(|tx| Ok(Event::PendingTransactions(tx)))
-    //     .boxed();
-
-    let block_stream: stream::BoxStream<'_, Result<Event>> = client
-        .subscribe_blocks()
-        .await?
-        .map(|block| Ok(Event::Block(block)))
-        .boxed();
-
-    info!("Subscribed to pending transactions");
-
-    // Merge the streams into a single stream.
-    let combined_stream = stream::select_all(vec![log_stream, block_stream]);
-
-    info!("Combined streams");
-
-    combined_stream
-        .for_each(|event| async {
-            let client = Arc::clone(&client);
-            let anvil = Arc::clone(&anvil);
-            let last_timestamp = Arc::clone(&last_block_timestamp);
-
-            match event {
-                Ok(Event::Log(log)) => match uniswap_v3_events::decode_poolcreated_event(&log) {
-                    Ok(pool_created_event) => {
-                        info!("pool created event {:#?}", pool_created_event);
-                        let last_time = last_timestamp.lock().await;
-
-                        if let Err(error) = add_validate_buy_new_token(
-                            &pool_created_event,
-                            &client,
-                            &anvil,
-                            last_time.clone(),
-                        )
-                        .await
-                        {
-                            warn!("Could not run add_validate_buy_new_token => {}", error);
-                        }
-                    }
-                    Err(error) => error!("error extracting pool created event => {}", error),
-                },
-                Ok(Event::Block(block)) => {
-                    info!("NEW BLOCK ===> {}", block.timestamp);
-                    let mut last_time = last_timestamp.lock().await;
-                    let current_block_timestamp = block.timestamp.as_u32();
-
-                    *last_time = current_block_timestamp;
-
-                    // check token liquidty
-                    if let Err(error) = check_all_tokens_and_update_if_are_tradable(&client).await {
-                        error!("could not check token tradability => {}", error);
-                    }
-
-                    if let Err(error) =
-                        buy_eligible_tokens_on_anvil(&anvil, current_block_timestamp).await
-                    {
-                        error!("error running buy_eligible_tokens_on_anvil => {}", error);
-                    }
-
-                    if let Err(error) =
-                        sell_eligible_tokens_on_anvil(&anvil, current_block_timestamp).await
-                    {
-                        error!("error running sell_eligible_tokens_on_anvil => {}", error);
-                    }
-                }
-                Err(e) => error!("Error: {:?}", e),
-            }
-        })
-        .await;
+
+    // RELOAD TOKENS AND OPEN POSITIONS FROM THE PREVIOUS RUN, ONE JOURNAL PER CHAIN
+    let store_dir =
+        std::env::var("TOKEN_STORE_DIR").unwrap_or_else(|_| DEFAULT_TOKEN_STORE_DIR.to_string());
+    let (registry, stored_tokens) = TokenRegistry::open(&store_dir, chain)?;
+    info!("loaded {} tokens from {}", stored_tokens, store_dir);
+    reconcile_positions(&registry, &anvil).await?;
+
+    let mut state = EventLoopState::new(
+        registry,
+        last_block_timestamp,
+        initial_block.number.unwrap_or_default().as_u64(),
+    );
+
+    if let Some(session_path) = chain_var("RECORD_SESSION", chain, multi_chain) {
+        info!("recording session to {}", session_path);
+        state = state.with_recorder(SessionRecorder::create(&session_path)?);
+    }
+
+    // BACKFILL POOLS CREATED WHILE THE BOT WAS OFFLINE
+    if let Some(from_block) = chain_var("BACKFILL_FROM_BLOCK", chain, multi_chain) {
+        let from_block: u64 = from_block.parse()?;
+        let to_block: u64 = match chain_var("BACKFILL_TO_BLOCK", chain, multi_chain) {
+            Some(to_block) => to_block.parse()?,
+            None => initial_block.number.unwrap_or_default().as_u64(),
+        };
+        backfill::backfill_pool_created_events(
+            &state.registry,
+            &client,
+            &anvil.strategy.current().launch_price_bounds,
+            from_block,
+            to_block,
+        )
+        .await?;
+    }
+
+    // runs until the process is killed, reconnecting whenever the websocket drops
+    run_event_loop_with_reconnect(&ws_url, &anvil, &state).await?;
 
     Ok(())
 }
+
+/// Replays `session_path` against an anvil fork pinned to `REPLAY_FORK_BLOCK` (or the
+/// latest block), reading chain state from the fork so reruns are deterministic
+async fn replay(session_path: &str, ws_url: &str, strategy: SharedStrategy) -> Result<()> {
+    let fork_block = match std::env::var("REPLAY_FORK_BLOCK") {
+        Ok(fork_block) => Some(fork_block.parse::<u64>()?),
+        Err(_) => None,
+    };
+
+    info!("Connecting to Anvil fork at block {:?}...", fork_block);
+    let anvil = AnvilSimulator::new_with_fork_block(ws_url, fork_block, strategy).await?;
+    let anvil = Arc::new(anvil);
+
+    let provider = Provider::<Ws>::connect(anvil.anvil.ws_endpoint()).await?;
+    let client = Arc::new(provider);
+
+    let fork_head = client.get_block(BlockNumber::Latest).await?.unwrap();
+    // replays start from an empty registry that is never persisted
+    let state = EventLoopState::new(
+        TokenRegistry::new(),
+        fork_head.timestamp.as_u32(),
+        fork_head.number.unwrap_or_default().as_u64(),
+    );
+
+    replay_session(session_path, &client, &anvil, &state).await
+}
+
+/// chains from `--chain <names>` / `--chain=<names>`, falling back to the CHAIN variable.
+/// Names are comma separated, `all` runs every configured chain.
+fn chains_to_run() -> Result<Vec<Chain>> {
+    let names = flag_argument("--chain", "CHAIN").unwrap_or_else(|| DEFAULT_CHAIN.to_string());
+
+    if names.trim() == "all" {
+        return Ok(supported_chains());
+    }
+
+    let mut chains: Vec<Chain> = vec![];
+    for name in names.split(',') {
+        let chain = parse_supported_chain(name)?;
+        if !chains.contains(&chain) {
+            chains.push(chain);
+        }
+    }
+
+    Ok(chains)
+}
+
+/// `snipper config check [--chain <names>] [--profile <name>]`
+fn config_check_requested() -> bool {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    args.windows(2).any(|args| args == ["config", "check"])
+}
+
+/// value of `--flag <value>` / `--flag=<value>`, falling back to the `env` variable
+fn flag_argument(flag: &str, env: &str) -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    let prefix = format!("{}=", flag);
+
+    while let Some(arg) = args.next() {
+        if arg == flag {
+            return args.next();
+        }
+        if let Some(value) = arg.strip_prefix(&prefix) {
+            return Some(value.to_string());
+        }
+    }
+
+    std::env::var(env).ok()
+}
+
+/// block numbers and session files differ per chain, so with several chains running
+/// `NAME` is read as `NAME_<CHAIN>`, e.g. BACKFILL_FROM_BLOCK_BASE
+fn chain_var(name: &str, chain: Chain, multi_chain: bool) -> Option<String> {
+    if multi_chain {
+        let chain_name = chain.to_string().to_uppercase().replace('-', "_");
+        std::env::var(format!("{}_{}", name, chain_name)).ok()
+    } else {
+        std::env::var(name).ok()
+    }
+}
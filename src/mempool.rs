@@ -0,0 +1,272 @@
+use crate::abi::uniswap_pair::UNISWAP_PAIR;
+use crate::abi::uniswap_pool::UNISWAP_V3_POOL;
+use crate::abi::uniswap_position_manager::{
+    CreateAndInitializePoolIfNecessaryCall, MintCall, MulticallCall,
+};
+use crate::abi::uniswap_router_v2::AddLiquidityETHCall;
+use crate::abi::uniswap_v3_factory::CreatePoolCall;
+use crate::data::contracts::CONTRACT;
+use crate::data::token_data::TokenRegistry;
+use crate::data::tokens::Erc20Token;
+use crate::event_loop::Event;
+use crate::swap::launch_price::LaunchPriceBounds;
+use crate::uniswap_v2_events::PairCreatedEvent;
+use crate::uniswap_v3_events::PoolCreatedEvent;
+use anyhow::Result;
+use ethers::abi::{encode, AbiDecode, Token};
+use ethers::providers::{Middleware, Provider, Ws};
+use ethers::types::{Address, Bytes, Transaction, H256, U256};
+use ethers::utils::{get_create2_address_from_hash, keccak256};
+use futures::{stream, StreamExt};
+use log::{debug, info};
+use std::sync::Arc;
+
+pub const V3_POOL_INIT_CODE_HASH: &str =
+    "0xe34f199b19b2b4f47f68442619d555527d244f78a3297ea89325f843f87b8b54";
+pub const V2_PAIR_INIT_CODE_HASH: &str =
+    "0x96e8ac4277198ff8b6f785478aa9a39f403cb768dd02cbee326c3e7da348845f";
+
+/// how many pending transactions are fetched from the node at once
+pub const PENDING_TX_CONCURRENCY: usize = 32;
+
+/// A pool or pair that a pending transaction is about to create or fund
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PendingLaunch {
+    V3Pool(PoolCreatedEvent),
+    V2Pair(PairCreatedEvent),
+}
+
+/// Contracts whose calldata can announce a new pool
+#[derive(Clone, Copy, Debug)]
+pub struct LaunchContracts {
+    pub uniswap_factory: Address,
+    /// None when the chain has no position manager / v2 deployment configured
+    pub uniswap_position_manager: Option<Address>,
+    pub uniswap_v2_factory: Option<Address>,
+    pub uniswap_v2_router: Option<Address>,
+    pub weth: Address,
+}
+
+impl LaunchContracts {
+    pub fn from_config() -> Self {
+        let addresses = CONTRACT.get_address();
+
+        Self {
+            uniswap_factory: addresses.uniswap_factory,
+            uniswap_position_manager: addresses.uniswap_position_manager,
+            uniswap_v2_factory: addresses.uniswap_v2_factory,
+            uniswap_v2_router: addresses.uniswap_v2_router,
+            weth: addresses.weth,
+        }
+    }
+
+    pub fn is_watched(&self, to: Address) -> bool {
+        to == self.uniswap_factory
+            || Some(to) == self.uniswap_position_manager
+            || Some(to) == self.uniswap_v2_router
+    }
+}
+
+/// Subscribes to pending transactions and yields only those sent to a launch contract
+pub async fn subscribe_to_launch_transactions(
+    client: &Arc<Provider<Ws>>,
+) -> Result<stream::BoxStream<'_, Result<Event>>> {
+    let contracts = LaunchContracts::from_config();
+
+    let tx_stream = client
+        .subscribe_pending_txs()
+        .await?
+        .map(move |tx_hash| async move { client.get_transaction(tx_hash).await })
+        .buffer_unordered(PENDING_TX_CONCURRENCY)
+        .filter_map(move |tx| async move {
+            match tx {
+                Ok(Some(tx)) if tx.to.is_some_and(|to| contracts.is_watched(to)) => {
+                    Some(Ok(Event::PendingTransaction(tx)))
+                }
+                _ => None,
+            }
+        })
+        .boxed();
+
+    Ok(tx_stream)
+}
+
+/// Decodes createPool, position manager mint / createAndInitializePoolIfNecessary
+/// (including inside multicall) and addLiquidityETH calldata
+pub fn decode_pending_launches(
+    tx: &Transaction,
+    contracts: &LaunchContracts,
+) -> Vec<PendingLaunch> {
+    let to = match tx.to {
+        Some(to) => to,
+        None => return vec![],
+    };
+
+    if to == contracts.uniswap_factory {
+        CreatePoolCall::decode(&tx.input)
+            .map(|call| vec![v3_launch(call.token_a, call.token_b, call.fee, contracts)])
+            .unwrap_or_default()
+    } else if Some(to) == contracts.uniswap_position_manager {
+        decode_position_manager_call(&tx.input, contracts)
+    } else if Some(to) == contracts.uniswap_v2_router {
+        // the pair address can't be derived without the factory
+        let Some(factory) = contracts.uniswap_v2_factory else {
+            return vec![];
+        };
+        AddLiquidityETHCall::decode(&tx.input)
+            .map(|call| vec![v2_launch(call.token, contracts.weth, factory)])
+            .unwrap_or_default()
+    } else {
+        vec![]
+    }
+}
+
+fn decode_position_manager_call(input: &Bytes, contracts: &LaunchContracts) -> Vec<PendingLaunch> {
+    if let Ok(call) = MintCall::decode(input) {
+        let params = call.params;
+        vec![v3_launch(
+            params.token_0,
+            params.token_1,
+            params.fee,
+            contracts,
+        )]
+    } else if let Ok(call) = CreateAndInitializePoolIfNecessaryCall::decode(input) {
+        vec![v3_launch(call.token_0, call.token_1, call.fee, contracts)]
+    } else if let Ok(call) = MulticallCall::decode(input) {
+        let mut launches: Vec<PendingLaunch> = vec![];
+        for inner_call in call.data.iter() {
+            for launch in decode_position_manager_call(inner_call, contracts) {
+                if !launches.contains(&launch) {
+                    launches.push(launch);
+                }
+            }
+        }
+        launches
+    } else {
+        vec![]
+    }
+}
+
+fn v3_launch(
+    token_a: Address,
+    token_b: Address,
+    fee: u32,
+    contracts: &LaunchContracts,
+) -> PendingLaunch {
+    let (token0, token1) = sort_tokens(token_a, token_b);
+
+    PendingLaunch::V3Pool(PoolCreatedEvent {
+        token0,
+        token1,
+        fee,
+        tick_spacing: fee_to_tick_spacing(fee),
+        pool: compute_v3_pool_address(contracts.uniswap_factory, token0, token1, fee),
+    })
+}
+
+fn v2_launch(token_a: Address, token_b: Address, factory: Address) -> PendingLaunch {
+    let (token0, token1) = sort_tokens(token_a, token_b);
+
+    PendingLaunch::V2Pair(PairCreatedEvent {
+        token0,
+        token1,
+        pair: compute_v2_pair_address(factory, token0, token1),
+    })
+}
+
+pub fn sort_tokens(token_a: Address, token_b: Address) -> (Address, Address) {
+    if token_a < token_b {
+        (token_a, token_b)
+    } else {
+        (token_b, token_a)
+    }
+}
+
+pub fn fee_to_tick_spacing(fee: u32) -> i32 {
+    match fee {
+        100 => 1,
+        500 => 10,
+        3000 => 60,
+        10000 => 200,
+        _ => 0,
+    }
+}
+
+pub fn compute_v3_pool_address(
+    factory: Address,
+    token0: Address,
+    token1: Address,
+    fee: u32,
+) -> Address {
+    let salt = keccak256(encode(&[
+        Token::Address(token0),
+        Token::Address(token1),
+        Token::Uint(U256::from(fee)),
+    ]));
+    let init_code_hash: H256 = V3_POOL_INIT_CODE_HASH.parse().unwrap();
+
+    get_create2_address_from_hash(factory, salt, init_code_hash)
+}
+
+pub fn compute_v2_pair_address(factory: Address, token0: Address, token1: Address) -> Address {
+    let salt = keccak256([token0.as_bytes(), token1.as_bytes()].concat());
+    let init_code_hash: H256 = V2_PAIR_INIT_CODE_HASH.parse().unwrap();
+
+    get_create2_address_from_hash(factory, salt, init_code_hash)
+}
+
+/// Saves the token of a pending launch unless its pool is already live with liquidity,
+/// so routine liquidity adds to old pools are ignored
+pub async fn pre_register_pending_launch(
+    registry: &TokenRegistry,
+    launch: &PendingLaunch,
+    bounds: &LaunchPriceBounds,
+    client: &Arc<Provider<Ws>>,
+) -> Result<Option<Erc20Token>> {
+    let pool_address = match launch {
+        PendingLaunch::V3Pool(event) => event.pool,
+        PendingLaunch::V2Pair(event) => event.pair,
+    };
+
+    let pool_code = client.get_code(pool_address, None).await?;
+    if !pool_code.is_empty() && pool_has_liquidity(launch, client).await? {
+        debug!("pool {:?} already has liquidity, skipping", pool_address);
+        return Ok(None);
+    }
+
+    let token = match launch {
+        PendingLaunch::V3Pool(event) => {
+            registry
+                .get_and_save_erc20_by_token_address(event, bounds, client)
+                .await?
+        }
+        PendingLaunch::V2Pair(event) => {
+            registry
+                .get_and_save_v2_erc20_by_token_address(event, bounds, client)
+                .await?
+        }
+    };
+
+    if let Some(token) = &token {
+        info!(
+            "pre-registered {} ({:?}) from pending transaction",
+            token.name, token.address
+        );
+    }
+
+    Ok(token)
+}
+
+async fn pool_has_liquidity(launch: &PendingLaunch, client: &Arc<Provider<Ws>>) -> Result<bool> {
+    match launch {
+        PendingLaunch::V3Pool(event) => {
+            let pool = UNISWAP_V3_POOL::new(event.pool, client.clone());
+            Ok(pool.liquidity().call().await? > 0)
+        }
+        PendingLaunch::V2Pair(event) => {
+            let pair = UNISWAP_PAIR::new(event.pair, client.clone());
+            let (reserve_0, reserve_1, _) = pair.get_reserves().call().await?;
+            Ok(reserve_0 > 0 && reserve_1 > 0)
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use crate::abi::multicall3::{Call3, MULTICALL3};
+use anyhow::{anyhow, Result};
+use ethers::abi::{self, Detokenize, Function, ParamType, Token};
+use ethers::contract::FunctionCall;
+use ethers::providers::Middleware;
+use ethers::types::{Address, Bytes};
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+/// Multicall3 is deployed at the same address on every chain
+pub const MULTICALL3_ADDRESS: &str = "0xcA11bde05977b3631167028862bE2a173976CA11";
+/// sub-calls sent in one eth_call, bigger batches are split so no single call runs out of gas
+pub const MAX_CALLS_PER_BATCH: usize = 500;
+
+// Error(string), what require / revert with a message return
+const REVERT_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// Collects contract calls and sends them through Multicall3 `aggregate3`, so reads for
+/// many tokens take one eth_call. A failing sub-call only fails its own result.
+pub struct Multicall<M> {
+    client: Arc<M>,
+    calls: Vec<(Call3, Function)>,
+}
+
+/// which call of a `Multicall` a result belongs to and what it decodes into
+pub struct CallHandle<D> {
+    index: usize,
+    output: PhantomData<D>,
+}
+
+/// result of every call in a `Multicall`, in the order they were added
+pub struct MulticallResults {
+    results: Vec<(Function, Result<Bytes, String>)>,
+}
+
+impl<M: Middleware + 'static> Multicall<M> {
+    pub fn new(client: &Arc<M>) -> Self {
+        Self {
+            client: client.clone(),
+            calls: vec![],
+        }
+    }
+
+    /// queues `call`, e.g. `multicall.add(erc20.symbol())`
+    pub fn add<B, C, D: Detokenize>(&mut self, call: FunctionCall<B, C, D>) -> CallHandle<D> {
+        let call3 = Call3 {
+            target: call.tx.to_addr().copied().unwrap_or_default(),
+            allow_failure: true,
+            call_data: call.tx.data().cloned().unwrap_or_default(),
+        };
+        self.calls.push((call3, call.function));
+
+        CallHandle {
+            index: self.calls.len() - 1,
+            output: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.calls.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.calls.is_empty()
+    }
+
+    /// Sends every queued call, MAX_CALLS_PER_BATCH per eth_call. Errors only when a
+    /// whole eth_call fails, sub-call failures are returned by `MulticallResults::get`.
+    pub async fn call(self) -> Result<MulticallResults> {
+        let multicall_address: Address = MULTICALL3_ADDRESS.parse()?;
+        let multicall = MULTICALL3::new(multicall_address, self.client.clone());
+
+        let mut results = Vec::with_capacity(self.calls.len());
+        for batch in self.calls.chunks(MAX_CALLS_PER_BATCH) {
+            let calls: Vec<Call3> = batch.iter().map(|(call, _)| call.clone()).collect();
+            let returned = multicall.aggregate_3(calls).call().await?;
+
+            if returned.len() != batch.len() {
+                return Err(anyhow!(
+                    "multicall returned {} results for {} calls",
+                    returned.len(),
+                    batch.len()
+                ));
+            }
+
+            for ((call, function), (success, return_data)) in batch.iter().zip(returned) {
+                let result = if success {
+                    Ok(return_data)
+                } else {
+                    Err(revert_message(call.target, function, &return_data))
+                };
+                results.push((function.clone(), result));
+            }
+        }
+
+        Ok(MulticallResults { results })
+    }
+}
+
+impl MulticallResults {
+    /// decoded result of the call behind `handle`, or why that call failed
+    pub fn get<D: Detokenize>(&self, handle: &CallHandle<D>) -> Result<D> {
+        let return_data = self.get_raw(handle)?;
+        let (function, _) = &self.results[handle.index];
+
+        // calls to an address without code succeed with no data
+        let tokens = function.decode_output(&return_data).map_err(|error| {
+            anyhow!(
+                "{}() returned undecodable data {} => {}",
+                function.name,
+                return_data,
+                error
+            )
+        })?;
+
+        Ok(D::from_tokens(tokens)?)
+    }
+
+    /// undecoded return data of the call behind `handle`, for outputs that don't match the ABI
+    pub fn get_raw<D>(&self, handle: &CallHandle<D>) -> Result<Bytes> {
+        let (_, result) = self
+            .results
+            .get(handle.index)
+            .ok_or_else(|| anyhow!("no multicall result {}", handle.index))?;
+
+        result.clone().map_err(|error| anyhow!(error))
+    }
+}
+
+fn revert_message(target: Address, function: &Function, return_data: &Bytes) -> String {
+    match revert_reason(return_data) {
+        Some(reason) => format!("{}() on {:?} reverted: {}", function.name, target, reason),
+        None => format!("{}() on {:?} reverted", function.name, target),
+    }
+}
+
+/// message of an `Error(string)` revert
+pub fn revert_reason(data: &Bytes) -> Option<String> {
+    let encoded = data.strip_prefix(&REVERT_SELECTOR)?;
+
+    match abi::decode(&[ParamType::String], encoded).ok()?.pop()? {
+        Token::String(reason) => Some(reason),
+        _ => None,
+    }
+}
@@ -0,0 +1,151 @@
+use anyhow::{anyhow, Result};
+use ethers::providers::{Middleware, Provider, Ws};
+use ethers::types::{Block, TxHash, H256};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// number of recent block hashes kept to detect reorgs
+pub const REORG_WINDOW: usize = 64;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reorg {
+    /// highest block still shared by the old and new chain
+    pub common_ancestor: u64,
+    /// (number, hash) of every block that was replaced, oldest first
+    pub removed_blocks: Vec<(u64, H256)>,
+    pub new_head: u64,
+}
+
+impl Reorg {
+    pub fn depth(&self) -> usize {
+        self.removed_blocks.len()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BlockCheck {
+    /// block already tracked with the same hash
+    Duplicate,
+    /// block builds on the tracked chain (or follows a gap)
+    Extended,
+    /// block replaced one or more tracked blocks
+    Reorg(Reorg),
+    /// the tracked parent has a different hash, push the new parent first
+    UnknownParent { number: u64, hash: H256 },
+}
+
+/// Sliding window of recent block hashes keyed by block number
+pub struct BlockWindow {
+    hashes: BTreeMap<u64, H256>,
+    capacity: usize,
+}
+
+impl Default for BlockWindow {
+    fn default() -> Self {
+        Self::new(REORG_WINDOW)
+    }
+}
+
+impl BlockWindow {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            hashes: BTreeMap::new(),
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn hash_at(&self, number: u64) -> Option<H256> {
+        self.hashes.get(&number).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    pub fn push(&mut self, number: u64, hash: H256, parent_hash: H256) -> BlockCheck {
+        if self.hash_at(number) == Some(hash) {
+            return BlockCheck::Duplicate;
+        }
+
+        if let Some(tracked_parent) = number.checked_sub(1).and_then(|n| self.hash_at(n)) {
+            if tracked_parent != parent_hash {
+                return BlockCheck::UnknownParent {
+                    number: number - 1,
+                    hash: parent_hash,
+                };
+            }
+        }
+
+        // anything at or above this height belongs to the old chain
+        let removed_blocks: Vec<(u64, H256)> = self.hashes.split_off(&number).into_iter().collect();
+
+        self.hashes.insert(number, hash);
+        while self.hashes.len() > self.capacity {
+            self.hashes.pop_first();
+        }
+
+        if removed_blocks.is_empty() {
+            BlockCheck::Extended
+        } else {
+            BlockCheck::Reorg(Reorg {
+                common_ancestor: number.saturating_sub(1),
+                removed_blocks,
+                new_head: number,
+            })
+        }
+    }
+}
+
+/// Adds `block` to the window, fetching replaced parents from the node until the new
+/// chain joins the tracked one. Returns the reorg if any tracked blocks were replaced.
+pub async fn track_block(
+    client: &Arc<Provider<Ws>>,
+    window: &mut BlockWindow,
+    block: &Block<TxHash>,
+) -> Result<Option<Reorg>> {
+    let (number, hash) = match (block.number, block.hash) {
+        (Some(number), Some(hash)) => (number.as_u64(), hash),
+        // pending blocks have no number or hash yet
+        _ => return Ok(None),
+    };
+
+    let mut pending = vec![(number, hash, block.parent_hash)];
+    let mut reorg: Option<Reorg> = None;
+
+    while let Some(&(number, hash, parent_hash)) = pending.last() {
+        match window.push(number, hash, parent_hash) {
+            BlockCheck::UnknownParent { hash, .. } => {
+                let parent = client
+                    .get_block(hash)
+                    .await?
+                    .ok_or_else(|| anyhow!("parent block {:?} not found", hash))?;
+                let parent_number = parent
+                    .number
+                    .ok_or_else(|| anyhow!("parent block {:?} has no number", hash))?;
+
+                pending.push((parent_number.as_u64(), hash, parent.parent_hash));
+            }
+            BlockCheck::Reorg(found) => {
+                // the deepest replaced block is found first, later pushes only extend it
+                match reorg.as_mut() {
+                    Some(reorg) => reorg.removed_blocks.extend(found.removed_blocks),
+                    None => reorg = Some(found),
+                }
+                pending.pop();
+            }
+            BlockCheck::Duplicate | BlockCheck::Extended => {
+                pending.pop();
+            }
+        }
+    }
+
+    if let Some(reorg) = reorg.as_mut() {
+        reorg.new_head = number;
+    }
+
+    Ok(reorg)
+}
@@ -0,0 +1,429 @@
+use crate::swap::launch_price::LaunchPriceBounds;
+use anyhow::{anyhow, Result};
+use ethers::types::{Address, U256};
+use ethers::utils::{format_ether, parse_ether};
+use serde::Deserialize;
+use std::sync::{Arc, RwLock};
+use std::{collections::HashMap, fs};
+
+pub const STRATEGY_PATH: &str = "strategy.toml";
+
+/// profile used when neither --profile, STRATEGY_PROFILE nor default_profile pick one
+pub const DEFAULT_PROFILE: &str = "default";
+
+const BASIS_POINTS: u32 = 10_000;
+// the wrap and every swap pay at least the base transaction cost
+const MIN_GAS_LIMIT: u64 = 21_000;
+/// longest hold time accepted, 30 days, block timestamps are u32 seconds
+pub const MAX_HOLD_TIME_SECS: u32 = 30 * 24 * 60 * 60;
+
+/// How much to buy, how long to hold and what to pay for it, one named profile of
+/// strategy.toml resolved and checked
+#[derive(Clone, Debug, PartialEq)]
+pub struct Strategy {
+    pub profile: String,
+    /// WETH (or ETH on v2) spent on every buy, in wei
+    pub buy_amount: U256,
+    /// seconds after the purchase a token is sold
+    pub hold_time_secs: u32,
+    /// how far below the quote a swap may fill, 200 = 2%
+    pub slippage_bps: u32,
+    pub buy_gas_limit: u64,
+    /// buys routed through a quote token cross two pools
+    pub multihop_buy_gas_limit: u64,
+    pub sell_gas_limit: u64,
+    /// ETH wrapped into WETH and approved for the router when the anvil account is set up
+    pub wrap_amount: U256,
+    pub wrap_gas_limit: u64,
+    /// tokens that are never bought
+    pub blacklist: Vec<Address>,
+    /// WETH bought and sold straight back on a reverted snapshot before every buy
+    pub honeypot_test_amount: U256,
+    /// most the test round trip may lose before the token is rejected, 1500 = 15%
+    pub max_round_trip_loss_bps: u32,
+    /// price and fully diluted valuation a v3 pool may launch at, in WETH
+    pub launch_price_bounds: LaunchPriceBounds,
+}
+
+impl Strategy {
+    /// Parses strategy.toml and returns `profile`, falling back to the file's
+    /// default_profile. Every profile is checked, the error lists every problem.
+    pub fn from_toml(config: &str, profile: Option<&str>) -> Result<Self> {
+        let config: RawStrategyConfig = toml::from_str(config)?;
+
+        let mut names: Vec<&String> = config.profiles.keys().collect();
+        names.sort();
+
+        let mut problems: Vec<String> = vec![];
+        let mut strategies = HashMap::<&str, Strategy>::new();
+        for name in names {
+            if let Some(strategy) = config.profiles[name].check(name, &mut problems) {
+                strategies.insert(name, strategy);
+            }
+        }
+
+        if !problems.is_empty() {
+            return Err(anyhow!(
+                "{} problem(s) in strategy config:\n  {}",
+                problems.len(),
+                problems.join("\n  ")
+            ));
+        }
+
+        let profile = profile
+            .or(config.default_profile.as_deref())
+            .unwrap_or(DEFAULT_PROFILE);
+
+        strategies.remove(profile).ok_or_else(|| {
+            let mut profiles: Vec<&str> = strategies.keys().copied().collect();
+            profiles.sort();
+
+            anyhow!(
+                "strategy profile {} not found, profiles are: {}",
+                profile,
+                profiles.join(", ")
+            )
+        })
+    }
+
+    /// `quoted` reduced by the allowed slippage
+    pub fn min_amount_out(&self, quoted: U256) -> U256 {
+        quoted * U256::from(BASIS_POINTS - self.slippage_bps) / U256::from(BASIS_POINTS)
+    }
+
+    pub fn is_blacklisted(&self, token: Address) -> bool {
+        self.blacklist.contains(&token)
+    }
+
+    /// every field by name, readable
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let bounds = &self.launch_price_bounds;
+        let blacklist = if self.blacklist.is_empty() {
+            "none".to_string()
+        } else {
+            let tokens: Vec<String> = self.blacklist.iter().map(|t| format!("{:?}", t)).collect();
+            tokens.join(", ")
+        };
+
+        vec![
+            (
+                "buy_amount",
+                format!("{} ETH", format_ether(self.buy_amount)),
+            ),
+            ("hold_time", format!("{}s", self.hold_time_secs)),
+            ("slippage", format!("{} bps", self.slippage_bps)),
+            ("buy_gas_limit", self.buy_gas_limit.to_string()),
+            (
+                "multihop_buy_gas_limit",
+                self.multihop_buy_gas_limit.to_string(),
+            ),
+            ("sell_gas_limit", self.sell_gas_limit.to_string()),
+            (
+                "wrap_amount",
+                format!("{} ETH", format_ether(self.wrap_amount)),
+            ),
+            ("wrap_gas_limit", self.wrap_gas_limit.to_string()),
+            ("blacklist", blacklist),
+            (
+                "honeypot_test_amount",
+                format!("{} ETH", format_ether(self.honeypot_test_amount)),
+            ),
+            (
+                "max_round_trip_loss",
+                format!("{} bps", self.max_round_trip_loss_bps),
+            ),
+            (
+                "launch_price",
+                weth_range(bounds.min_price_weth, bounds.max_price_weth),
+            ),
+            (
+                "launch_fdv",
+                weth_range(bounds.min_fdv_weth, bounds.max_fdv_weth),
+            ),
+        ]
+    }
+
+    /// `field: old -> new` for every field `updated` changes
+    pub fn diff(&self, updated: &Strategy) -> Vec<String> {
+        self.fields()
+            .into_iter()
+            .zip(updated.fields())
+            .filter(|((_, old), (_, new))| old != new)
+            .map(|((name, old), (_, new))| format!("{}: {} -> {}", name, old, new))
+            .collect()
+    }
+
+    /// Checks `updated` can replace this strategy while running. The account is funded
+    /// once when the anvil fork starts, so the wrap can't change without a restart.
+    pub fn check_reload(&self, updated: &Strategy) -> Result<()> {
+        let mut fixed: Vec<&str> = vec![];
+        if updated.profile != self.profile {
+            fixed.push("profile");
+        }
+        if updated.wrap_amount != self.wrap_amount {
+            fixed.push("wrap_amount");
+        }
+        if updated.wrap_gas_limit != self.wrap_gas_limit {
+            fixed.push("wrap_gas_limit");
+        }
+
+        if !fixed.is_empty() {
+            return Err(anyhow!(
+                "{} can't change at runtime, restart to apply",
+                fixed.join(", ")
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// The running strategy, shared by every chain and swapped in place on reload
+#[derive(Clone, Debug)]
+pub struct SharedStrategy {
+    strategy: Arc<RwLock<Strategy>>,
+}
+
+impl SharedStrategy {
+    pub fn new(strategy: Strategy) -> Self {
+        Self {
+            strategy: Arc::new(RwLock::new(strategy)),
+        }
+    }
+
+    /// copy of the strategy as it is right now
+    pub fn current(&self) -> Strategy {
+        self.strategy
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Swaps in `updated` if `check_reload` allows it, returning the diff
+    pub fn reload(&self, updated: Strategy) -> Result<Vec<String>> {
+        let mut strategy = self
+            .strategy
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        strategy.check_reload(&updated)?;
+        let diff = strategy.diff(&updated);
+        *strategy = updated;
+
+        Ok(diff)
+    }
+}
+
+/// Reads strategy.toml once at startup and resolves `profile`
+pub fn load_strategy(profile: Option<&str>) -> Result<Strategy> {
+    let config = fs::read_to_string(STRATEGY_PATH)
+        .map_err(|error| anyhow!("failed to read {} => {}", STRATEGY_PATH, error))?;
+
+    Strategy::from_toml(&config, profile)
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawStrategyConfig {
+    default_profile: Option<String>,
+    #[serde(default)]
+    profiles: HashMap<String, RawStrategy>,
+}
+
+/// one profile as written, fields left out keep the values below
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawStrategy {
+    buy_amount_eth: String,
+    hold_time_secs: u32,
+    slippage_bps: u32,
+    buy_gas_limit: u64,
+    multihop_buy_gas_limit: u64,
+    sell_gas_limit: u64,
+    wrap_amount_eth: String,
+    wrap_gas_limit: u64,
+    blacklist: Vec<String>,
+    honeypot_test_amount_eth: String,
+    max_round_trip_loss_bps: u32,
+    min_launch_price_weth: Option<f64>,
+    max_launch_price_weth: Option<f64>,
+    min_launch_fdv_weth: Option<f64>,
+    max_launch_fdv_weth: Option<f64>,
+}
+
+impl Default for RawStrategy {
+    fn default() -> Self {
+        Self {
+            buy_amount_eth: "0.1".to_string(),
+            hold_time_secs: 60,
+            slippage_bps: 200,
+            buy_gas_limit: 300_000,
+            multihop_buy_gas_limit: 500_000,
+            sell_gas_limit: 1_000_000,
+            wrap_amount_eth: "10.0".to_string(),
+            wrap_gas_limit: 300_000,
+            blacklist: vec![],
+            honeypot_test_amount_eth: "0.001".to_string(),
+            max_round_trip_loss_bps: 1_500,
+            min_launch_price_weth: None,
+            max_launch_price_weth: None,
+            min_launch_fdv_weth: None,
+            max_launch_fdv_weth: None,
+        }
+    }
+}
+
+impl RawStrategy {
+    fn check(&self, profile: &str, problems: &mut Vec<String>) -> Option<Strategy> {
+        let mut found: Vec<String> = vec![];
+
+        let buy_amount = ether_amount("buy_amount_eth", &self.buy_amount_eth, &mut found);
+        let wrap_amount = ether_amount("wrap_amount_eth", &self.wrap_amount_eth, &mut found);
+        if let (Some(buy_amount), Some(wrap_amount)) = (buy_amount, wrap_amount) {
+            if buy_amount > wrap_amount {
+                found.push(format!(
+                    "buy_amount_eth {} is more than wrap_amount_eth {}",
+                    self.buy_amount_eth, self.wrap_amount_eth
+                ));
+            }
+        }
+
+        let honeypot_test_amount = ether_amount(
+            "honeypot_test_amount_eth",
+            &self.honeypot_test_amount_eth,
+            &mut found,
+        );
+        if let (Some(test_amount), Some(wrap_amount)) = (honeypot_test_amount, wrap_amount) {
+            if test_amount > wrap_amount {
+                found.push(format!(
+                    "honeypot_test_amount_eth {} is more than wrap_amount_eth {}",
+                    self.honeypot_test_amount_eth, self.wrap_amount_eth
+                ));
+            }
+        }
+
+        if self.hold_time_secs > MAX_HOLD_TIME_SECS {
+            found.push(format!(
+                "hold_time_secs {} is more than {}",
+                self.hold_time_secs, MAX_HOLD_TIME_SECS
+            ));
+        }
+
+        if self.slippage_bps >= BASIS_POINTS {
+            found.push(format!(
+                "slippage_bps {} has to be below {}",
+                self.slippage_bps, BASIS_POINTS
+            ));
+        }
+
+        if self.max_round_trip_loss_bps > BASIS_POINTS {
+            found.push(format!(
+                "max_round_trip_loss_bps {} is more than {}",
+                self.max_round_trip_loss_bps, BASIS_POINTS
+            ));
+        }
+
+        let launch_bounds = [
+            (
+                ("min_launch_price_weth", self.min_launch_price_weth),
+                ("max_launch_price_weth", self.max_launch_price_weth),
+            ),
+            (
+                ("min_launch_fdv_weth", self.min_launch_fdv_weth),
+                ("max_launch_fdv_weth", self.max_launch_fdv_weth),
+            ),
+        ];
+        for ((min_name, min), (max_name, max)) in launch_bounds {
+            for (name, bound) in [(min_name, min), (max_name, max)] {
+                if let Some(bound) = bound.filter(|bound| !bound.is_finite() || *bound < 0.0) {
+                    found.push(format!("{} {} has to be 0 or more", name, bound));
+                }
+            }
+            if let (Some(min), Some(max)) = (min, max) {
+                if min > max {
+                    found.push(format!(
+                        "{} {} is more than {} {}",
+                        min_name, min, max_name, max
+                    ));
+                }
+            }
+        }
+
+        let gas_limits = [
+            ("buy_gas_limit", self.buy_gas_limit),
+            ("multihop_buy_gas_limit", self.multihop_buy_gas_limit),
+            ("sell_gas_limit", self.sell_gas_limit),
+            ("wrap_gas_limit", self.wrap_gas_limit),
+        ];
+        for (name, gas_limit) in gas_limits {
+            if gas_limit < MIN_GAS_LIMIT {
+                found.push(format!("{} {} is below {}", name, gas_limit, MIN_GAS_LIMIT));
+            }
+        }
+
+        let mut blacklist: Vec<Address> = vec![];
+        for token in self.blacklist.iter() {
+            match token.trim().parse::<Address>() {
+                Ok(token) => blacklist.push(token),
+                Err(_) => found.push(format!("blacklist entry {:?} is not an address", token)),
+            }
+        }
+
+        if !found.is_empty() {
+            problems.extend(
+                found
+                    .iter()
+                    .map(|problem| format!("[{}] {}", profile, problem)),
+            );
+            return None;
+        }
+
+        Some(Strategy {
+            profile: profile.to_string(),
+            buy_amount: buy_amount?,
+            hold_time_secs: self.hold_time_secs,
+            slippage_bps: self.slippage_bps,
+            buy_gas_limit: self.buy_gas_limit,
+            multihop_buy_gas_limit: self.multihop_buy_gas_limit,
+            sell_gas_limit: self.sell_gas_limit,
+            wrap_amount: wrap_amount?,
+            wrap_gas_limit: self.wrap_gas_limit,
+            blacklist,
+            honeypot_test_amount: honeypot_test_amount?,
+            max_round_trip_loss_bps: self.max_round_trip_loss_bps,
+            launch_price_bounds: LaunchPriceBounds {
+                min_price_weth: self.min_launch_price_weth,
+                max_price_weth: self.max_launch_price_weth,
+                min_fdv_weth: self.min_launch_fdv_weth,
+                max_fdv_weth: self.max_launch_fdv_weth,
+            },
+        })
+    }
+}
+
+/// an optional min and max as written in `fields`
+fn weth_range(min: Option<f64>, max: Option<f64>) -> String {
+    match (min, max) {
+        (None, None) => "any".to_string(),
+        (Some(min), None) => format!("{} WETH or more", min),
+        (None, Some(max)) => format!("up to {} WETH", max),
+        (Some(min), Some(max)) => format!("{} to {} WETH", min, max),
+    }
+}
+
+fn ether_amount(name: &str, value: &str, found: &mut Vec<String>) -> Option<U256> {
+    match parse_ether(value.trim()) {
+        Ok(amount) if !amount.is_zero() => Some(amount),
+        Ok(_) => {
+            found.push(format!("{} has to be more than 0", name));
+            None
+        }
+        Err(error) => {
+            found.push(format!(
+                "{} {:?} is not an ETH amount => {}",
+                name, value, error
+            ));
+            None
+        }
+    }
+}
@@ -1,16 +1,19 @@
 use crate::abi::erc20::ERC20;
 use crate::abi::uniswap_quoter::{QuoteExactInputSingleParams, UNISWAP_QUOTER};
+use crate::abi::uniswap_router_v2::UNISWAP_V2_ROUTER;
 use crate::abi::uniswap_v3_factory::UNISWAP_V3_FACTORY;
-use crate::abi::uniswap_v3_router::{ExactInputSingleParams, UNISWAP_V3_ROUTER};
-use crate::data::contracts::{CHAIN, CONTRACT};
-use crate::data::tokens::Erc20Token;
+use crate::abi::uniswap_v3_router::{ExactInputParams, ExactInputSingleParams, UNISWAP_V3_ROUTER};
+use crate::data::contracts::{current_chain, CONTRACT};
+use crate::data::tokens::{Dex, Erc20Token};
+use crate::strategy::SharedStrategy;
+use crate::swap::transfer_tax::after_tax;
 use crate::utils::type_conversion::{
     address_to_string, get_function_selector, u256_to_f64_with_decimals,
 };
 use anyhow::Result;
 use ethers::types::{
-    CallFrame, GethDebugTracerType, GethDebugTracingOptions, GethTrace, GethTraceFrame,
-    TransactionRequest, H256, U256,
+    BlockNumber, Bytes, CallFrame, Chain, GethDebugTracerType, GethDebugTracingOptions, GethTrace,
+    GethTraceFrame, TransactionRequest, H256, U256,
 };
 use ethers::utils::format_units;
 use ethers::{
@@ -30,14 +33,31 @@ pub struct AnvilSimulator {
     pub client: Arc<SignerMiddleware<Provider<Ws>, Wallet<SigningKey>>>,
     pub anvil: AnvilInstance,
     pub from_address: Address,
+    /// chain that was forked, prefixes every report
+    pub chain: Chain,
+    /// buy size, slippage and gas limits of every simulated trade, reloadable
+    pub strategy: SharedStrategy,
 }
 
 impl AnvilSimulator {
-    pub async fn new(rpc_url: &str) -> Result<Self> {
+    pub async fn new(rpc_url: &str, strategy: SharedStrategy) -> Result<Self> {
+        Self::new_with_fork_block(rpc_url, None, strategy).await
+    }
+
+    /// same as `new` but pins the fork to `fork_block` instead of the latest block
+    pub async fn new_with_fork_block(
+        rpc_url: &str,
+        fork_block: Option<u64>,
+        strategy: SharedStrategy,
+    ) -> Result<Self> {
         // Main network provider   // Configure Anvil with forking
-        let anvil = Anvil::new()
-            .fork(rpc_url) // URL of your Geth node
-            .spawn();
+        let mut anvil = Anvil::new().fork(rpc_url); // URL of your Geth node
+
+        if let Some(fork_block) = fork_block {
+            anvil = anvil.fork_block_number(fork_block);
+        }
+
+        let anvil = anvil.spawn();
 
         // setup mock sender
         let from_address: Address = anvil.addresses()[0];
@@ -49,7 +69,8 @@ impl AnvilSimulator {
         let provider = Provider::<Ws>::connect(anvil_ws_url).await?;
 
         // Create a wallet with the private key
-        let wallet = Wallet::from(from_private_key).with_chain_id(CHAIN);
+        let chain = current_chain()?;
+        let wallet = Wallet::from(from_private_key).with_chain_id(chain);
 
         // Create the SignerMiddleware
         let client = Arc::new(SignerMiddleware::new(provider, wallet));
@@ -69,6 +90,8 @@ impl AnvilSimulator {
             client,
             anvil,
             from_address,
+            chain,
+            strategy,
         };
 
         simulator.prepare_account().await?;
@@ -78,8 +101,8 @@ impl AnvilSimulator {
 
     /// Prepares the test account by converting some ETH to WETH and approving the router.
     pub async fn prepare_account(&self) -> anyhow::Result<()> {
-        let swap_router_address: Address = CONTRACT.get_address().uniswap_swap_router.parse()?;
-        let weth_address: Address = CONTRACT.get_address().weth.parse()?;
+        let swap_router_address: Address = CONTRACT.get_address().uniswap_swap_router;
+        let weth_address: Address = CONTRACT.get_address().weth;
         let weth_contract = ERC20::new(weth_address, self.client.clone());
 
         // Impersonate the from_address to send transactions from it
@@ -88,8 +111,8 @@ impl AnvilSimulator {
             .request::<_, ()>("anvil_impersonateAccount", [self.from_address])
             .await?;
 
-        // Convert 10 ETH to WETH using wrapETH method on the router
-        let wrap_amount = ethers::utils::parse_ether("10.0")?;
+        // Convert the strategy's wrap amount of ETH to WETH using wrapETH method on the router
+        let wrap_amount = self.strategy.current().wrap_amount;
 
         let eth_balance = self.client.get_balance(self.from_address, None).await?;
         debug!("ETH Balance of from_address: {}", eth_balance);
@@ -108,7 +131,7 @@ impl AnvilSimulator {
         let gas_price = self.client.provider().get_gas_price().await?;
         debug!("Current gas price: {}", gas_price);
 
-        // Wrap 10 ETH into WETH
+        // Wrap ETH into WETH
         let wrap_tx = self
             .client
             .provider()
@@ -118,7 +141,7 @@ impl AnvilSimulator {
                     .data(deposit_selector)
                     .value(wrap_amount)
                     .gas_price(gas_price)
-                    .gas(U256::from(300_000)),
+                    .gas(U256::from(self.strategy.current().wrap_gas_limit)),
                 None,
             )
             .await?;
@@ -127,9 +150,8 @@ impl AnvilSimulator {
             .await?
             .ok_or_else(|| anyhow::anyhow!("No wrapETH receipt received"))?;
 
-        let approval_amount = ethers::utils::parse_ether("10")?;
         weth_contract
-            .approve(swap_router_address, approval_amount)
+            .approve(swap_router_address, wrap_amount)
             .send()
             .await?;
 
@@ -151,8 +173,32 @@ impl AnvilSimulator {
     }
 
     pub async fn simulate_buying_token_for_weth(&self, token: &Erc20Token) -> Result<U256> {
-        let swap_router_address: Address = CONTRACT.get_address().uniswap_swap_router.parse()?;
-        let weth_address: Address = CONTRACT.get_address().weth.parse()?;
+        let amount_in = self.strategy.current().buy_amount;
+        self.simulate_buying_token_with_amount(token, amount_in)
+            .await
+    }
+
+    /// buys `token` for `amount_in` of WETH (ETH on v2), returning the token balance after
+    pub async fn simulate_buying_token_with_amount(
+        &self,
+        token: &Erc20Token,
+        amount_in: U256,
+    ) -> Result<U256> {
+        if token.dex == Dex::UniswapV2 {
+            return self
+                .simulate_buying_token_for_eth_on_v2(token, amount_in)
+                .await;
+        }
+
+        let swap_router_address: Address = CONTRACT.get_address().uniswap_swap_router;
+        let weth_address: Address = CONTRACT.get_address().weth;
+
+        if token.quote_token != weth_address {
+            return self
+                .simulate_buying_token_through_quote(token, amount_in)
+                .await;
+        }
+
         let mut new_token_balance = U256::from(0);
         let swap_router = UNISWAP_V3_ROUTER::new(swap_router_address, self.client.clone());
 
@@ -165,14 +211,21 @@ impl AnvilSimulator {
         println!("........................................................");
         self.get_weth_balance().await?;
         self.get_eth_balance().await?;
-        let amount_to_buy =
-            std::env::var("TOKEN_TO_BUY_IN_ETH").expect("TOKEN_TO_BUY_IN_ETH is not set in .env");
-        println!("buying {} WETH of {}", amount_to_buy, token.name);
-        let amount_in = ethers::utils::parse_ether(amount_to_buy)?;
+        let amount_to_buy = format_units(amount_in, 18u32)?;
+        println!(
+            "[{}] buying {} WETH of {}",
+            self.chain, amount_to_buy, token.name
+        );
 
         // calculate amount amount out and gas used
         let (amount_out_min, gas_used) = self
-            .get_amount_out_plus_gas_used(weth_address, token.address, amount_in, token.fee)
+            .get_amount_out_plus_gas_used(
+                weth_address,
+                token.address,
+                amount_in,
+                token.fee,
+                token.taxes.unwrap_or_default().buy_bps,
+            )
             .await?;
         let gas_cost = self.get_gas_cost(gas_used).await?;
 
@@ -197,7 +250,7 @@ impl AnvilSimulator {
         };
 
         info!("set gas limit for transaction");
-        let tx = tx.gas(U256::from(300_000));
+        let tx = tx.gas(U256::from(self.strategy.current().buy_gas_limit));
 
         // sent transaction
         info!("sending liquidate transcation");
@@ -248,8 +301,17 @@ impl AnvilSimulator {
     }
 
     pub async fn simulate_selling_token_for_weth(&self, token: &Erc20Token) -> Result<U256> {
-        let swap_router_address: Address = CONTRACT.get_address().uniswap_swap_router.parse()?;
-        let weth_address: Address = CONTRACT.get_address().weth.parse()?;
+        if token.dex == Dex::UniswapV2 {
+            return self.simulate_selling_token_for_eth_on_v2(token).await;
+        }
+
+        let swap_router_address: Address = CONTRACT.get_address().uniswap_swap_router;
+        let weth_address: Address = CONTRACT.get_address().weth;
+
+        if token.quote_token != weth_address {
+            return self.simulate_selling_token_through_quote(token).await;
+        }
+
         let token_contract = ERC20::new(token.address, self.client.clone());
 
         let mut new_token_balance = U256::from(0);
@@ -278,7 +340,13 @@ impl AnvilSimulator {
         // calculate amount amount out and gas used
         println!("........................................................");
         let (amount_out_min, gas_used) = self
-            .get_amount_out_plus_gas_used(token.address, weth_address, amount_to_sell, token.fee)
+            .get_amount_out_plus_gas_used(
+                token.address,
+                weth_address,
+                amount_to_sell,
+                token.fee,
+                token.taxes.unwrap_or_default().sell_bps,
+            )
             .await?;
         let gas_cost = self.get_gas_cost(gas_used).await?;
 
@@ -303,7 +371,7 @@ impl AnvilSimulator {
         };
 
         info!("set gas limit for transaction");
-        let tx = tx.gas(U256::from(1_000_000));
+        let tx = tx.gas(U256::from(self.strategy.current().sell_gas_limit));
 
         // sent transaction
         info!("sending liquidate transcation");
@@ -357,14 +425,427 @@ impl AnvilSimulator {
         Ok(new_token_balance)
     }
 
+    /// buys a token quoted in something other than WETH through WETH -> quote -> token
+    async fn simulate_buying_token_through_quote(
+        &self,
+        token: &Erc20Token,
+        amount_in: U256,
+    ) -> Result<U256> {
+        let swap_router_address: Address = CONTRACT.get_address().uniswap_swap_router;
+        let weth_address: Address = CONTRACT.get_address().weth;
+        let mut new_token_balance = U256::from(0);
+        let swap_router = UNISWAP_V3_ROUTER::new(swap_router_address, self.client.clone());
+
+        let path = encode_v3_path(
+            &[weth_address, token.quote_token, token.address],
+            &[quote_weth_pool_fee(token)?, token.fee],
+        );
+
+        // Impersonate the account you want to send the transaction from
+        self.client
+            .provider()
+            .request::<_, ()>("anvil_impersonateAccount", [self.from_address])
+            .await?;
+
+        println!("........................................................");
+        self.get_weth_balance().await?;
+        let amount_to_buy = format_units(amount_in, 18u32)?;
+        println!(
+            "[{}] buying {} WETH of {} through {:?}",
+            self.chain, amount_to_buy, token.name, token.quote_token
+        );
+
+        let (amount_out_min, gas_used) = self
+            .get_multihop_amount_out_plus_gas_used(
+                path.clone(),
+                amount_in,
+                token.taxes.unwrap_or_default().buy_bps,
+            )
+            .await?;
+        let gas_cost = self.get_gas_cost(gas_used).await?;
+        println!("calculated amount out min {}", amount_out_min);
+        println!("with gas cost of {} for transaction", gas_cost);
+        println!("........................................................");
+
+        let swap_params = ExactInputParams {
+            path,
+            recipient: self.from_address,
+            amount_in,
+            amount_out_minimum: amount_out_min,
+        };
+        debug!("swap params: {:?}", swap_params);
+
+        let tx = swap_router
+            .exact_input(swap_params)
+            .gas(U256::from(self.strategy.current().multihop_buy_gas_limit));
+
+        info!("sending multihop buy transaction");
+        match tx.send().await {
+            Ok(pending_tx) => {
+                info!("awaiting transaction receipt");
+                let receipt = pending_tx.await?.unwrap();
+                self.trace_transaction(receipt.transaction_hash).await?;
+
+                println!("........................................................");
+                println!("balance after buying {}...", token.name);
+                new_token_balance = self.get_token_balance(token).await?;
+                self.get_weth_balance().await?;
+                println!("........................................................");
+            }
+            Err(tx_err) => {
+                error!("Failed to send transaction: {:?}", tx_err);
+            }
+        }
+
+        self.client
+            .provider()
+            .request::<_, ()>("anvil_stopImpersonatingAccount", [self.from_address])
+            .await?;
+        Ok(new_token_balance)
+    }
+
+    /// sells a token quoted in something other than WETH through token -> quote -> WETH
+    async fn simulate_selling_token_through_quote(&self, token: &Erc20Token) -> Result<U256> {
+        let swap_router_address: Address = CONTRACT.get_address().uniswap_swap_router;
+        let weth_address: Address = CONTRACT.get_address().weth;
+        let token_contract = ERC20::new(token.address, self.client.clone());
+        let mut new_token_balance = U256::from(0);
+        let swap_router = UNISWAP_V3_ROUTER::new(swap_router_address, self.client.clone());
+
+        let path = encode_v3_path(
+            &[token.address, token.quote_token, weth_address],
+            &[token.fee, quote_weth_pool_fee(token)?],
+        );
+
+        // Impersonate the account you want to send the transaction from
+        self.client
+            .provider()
+            .request::<_, ()>("anvil_impersonateAccount", [self.from_address])
+            .await?;
+
+        println!("........................................................");
+        self.get_weth_balance().await?;
+        let amount_to_sell = self.get_token_balance(token).await?;
+
+        //approve swap router to trade token
+        token_contract
+            .approve(swap_router_address, amount_to_sell)
+            .send()
+            .await?;
+
+        let (amount_out_min, gas_used) = self
+            .get_multihop_amount_out_plus_gas_used(
+                path.clone(),
+                amount_to_sell,
+                token.taxes.unwrap_or_default().sell_bps,
+            )
+            .await?;
+        let gas_cost = self.get_gas_cost(gas_used).await?;
+
+        let amount_out_min_readable = format_units(amount_out_min, 18u32)?;
+        println!("calculated amount out min {}", amount_out_min_readable);
+        println!("with gas cost of {} for transaction", gas_cost);
+        println!("........................................................");
+
+        let swap_params = ExactInputParams {
+            path,
+            recipient: self.from_address,
+            amount_in: amount_to_sell,
+            amount_out_minimum: amount_out_min,
+        };
+        debug!("swap params: {:?}", swap_params);
+
+        let tx = swap_router
+            .exact_input(swap_params)
+            .gas(U256::from(self.strategy.current().sell_gas_limit));
+
+        info!("sending multihop sell transaction");
+        match tx.send().await {
+            Ok(pending_tx) => {
+                info!("awaiting transaction receipt");
+                let receipt = pending_tx.await?.unwrap();
+                self.trace_transaction(receipt.transaction_hash).await?;
+
+                println!("........................................................");
+                println!("balance AFTER to selling {}", token.name);
+                new_token_balance = self.get_token_balance(token).await?;
+                self.get_weth_balance().await?;
+                println!("........................................................");
+                self.get_current_profit_loss().await?;
+                println!("........................................................");
+            }
+            Err(tx_err) => {
+                error!("Failed to send transaction: {:?}", tx_err);
+            }
+        }
+
+        self.client
+            .provider()
+            .request::<_, ()>("anvil_stopImpersonatingAccount", [self.from_address])
+            .await?;
+        Ok(new_token_balance)
+    }
+
+    async fn simulate_buying_token_for_eth_on_v2(
+        &self,
+        token: &Erc20Token,
+        amount_in: U256,
+    ) -> Result<U256> {
+        let router_address = CONTRACT.get_address().uniswap_v2_router_address()?;
+        let weth_address: Address = CONTRACT.get_address().weth;
+        let mut new_token_balance = U256::from(0);
+        let router = UNISWAP_V2_ROUTER::new(router_address, self.client.clone());
+
+        // Impersonate the account you want to send the transaction from
+        self.client
+            .provider()
+            .request::<_, ()>("anvil_impersonateAccount", [self.from_address])
+            .await?;
+
+        println!("........................................................");
+        self.get_eth_balance().await?;
+        let amount_to_buy = format_units(amount_in, 18u32)?;
+        println!(
+            "[{}] buying {} ETH of {} on uniswap v2",
+            self.chain, amount_to_buy, token.name
+        );
+
+        let path = v2_path(weth_address, token, true);
+        let amount_out_min = self
+            .get_v2_amount_out(
+                amount_in,
+                path.clone(),
+                token.taxes.unwrap_or_default().buy_bps,
+            )
+            .await?;
+        let deadline = self.get_deadline().await?;
+        println!("calculated amount out min {}", amount_out_min);
+        println!("........................................................");
+
+        let tx = router
+            .swap_exact_eth_for_tokens_supporting_fee_on_transfer_tokens(
+                amount_out_min,
+                path,
+                self.from_address,
+                deadline,
+            )
+            .value(amount_in)
+            .gas(U256::from(self.strategy.current().buy_gas_limit));
+
+        info!("sending v2 buy transaction");
+        match tx.send().await {
+            Ok(pending_tx) => {
+                info!("awaiting transaction receipt");
+                let receipt = pending_tx.await?.unwrap();
+                self.trace_transaction(receipt.transaction_hash).await?;
+
+                println!("........................................................");
+                println!("balance after buying {}...", token.name);
+                new_token_balance = self.get_token_balance(token).await?;
+                self.get_eth_balance().await?;
+                println!("........................................................");
+            }
+            Err(tx_err) => {
+                error!("Failed to send transaction: {:?}", tx_err);
+            }
+        }
+
+        self.client
+            .provider()
+            .request::<_, ()>("anvil_stopImpersonatingAccount", [self.from_address])
+            .await?;
+        Ok(new_token_balance)
+    }
+
+    async fn simulate_selling_token_for_eth_on_v2(&self, token: &Erc20Token) -> Result<U256> {
+        let router_address = CONTRACT.get_address().uniswap_v2_router_address()?;
+        let weth_address: Address = CONTRACT.get_address().weth;
+        let token_contract = ERC20::new(token.address, self.client.clone());
+        let mut new_token_balance = U256::from(0);
+        let router = UNISWAP_V2_ROUTER::new(router_address, self.client.clone());
+
+        // Impersonate the account you want to send the transaction from
+        self.client
+            .provider()
+            .request::<_, ()>("anvil_impersonateAccount", [self.from_address])
+            .await?;
+
+        println!("........................................................");
+        self.get_eth_balance().await?;
+        let amount_to_sell = self.get_token_balance(token).await?;
+
+        //approve v2 router to trade token
+        token_contract
+            .approve(router_address, amount_to_sell)
+            .send()
+            .await?;
+
+        let path = v2_path(weth_address, token, false);
+        let amount_out_min = self
+            .get_v2_amount_out(
+                amount_to_sell,
+                path.clone(),
+                token.taxes.unwrap_or_default().sell_bps,
+            )
+            .await?;
+        let deadline = self.get_deadline().await?;
+
+        let amount_out_min_readable = format_units(amount_out_min, 18u32)?;
+        println!("calculated amount out min {}", amount_out_min_readable);
+        println!("........................................................");
+
+        let tx = router
+            .swap_exact_tokens_for_eth_supporting_fee_on_transfer_tokens(
+                amount_to_sell,
+                amount_out_min,
+                path,
+                self.from_address,
+                deadline,
+            )
+            .gas(U256::from(self.strategy.current().sell_gas_limit));
+
+        info!("sending v2 sell transaction");
+        match tx.send().await {
+            Ok(pending_tx) => {
+                info!("awaiting transaction receipt");
+                let receipt = pending_tx.await?.unwrap();
+                self.trace_transaction(receipt.transaction_hash).await?;
+
+                println!("........................................................");
+                println!("balance AFTER to selling {}", token.name);
+                new_token_balance = self.get_token_balance(token).await?;
+                self.get_eth_balance().await?;
+                println!("........................................................");
+                self.get_current_profit_loss().await?;
+                println!("........................................................");
+            }
+            Err(tx_err) => {
+                error!("Failed to send transaction: {:?}", tx_err);
+            }
+        }
+
+        self.client
+            .provider()
+            .request::<_, ()>("anvil_stopImpersonatingAccount", [self.from_address])
+            .await?;
+        Ok(new_token_balance)
+    }
+
+    async fn get_v2_amount_out(
+        &self,
+        amount_in: U256,
+        path: Vec<Address>,
+        tax_bps: u32,
+    ) -> anyhow::Result<U256> {
+        let amount_out = self.get_v2_quote(amount_in, path).await?;
+
+        // leave room for the strategy's slippage to account for token volatility,
+        // and for the token's measured tax, the router checks what actually arrives
+        let amount_out = after_tax(self.strategy.current().min_amount_out(amount_out), tax_bps);
+
+        Ok(amount_out)
+    }
+
+    async fn get_v2_quote(&self, amount_in: U256, path: Vec<Address>) -> anyhow::Result<U256> {
+        let router_address = CONTRACT.get_address().uniswap_v2_router_address()?;
+        let router = UNISWAP_V2_ROUTER::new(router_address, self.client.clone());
+
+        let amounts_out = router.get_amounts_out(amount_in, path).call().await?;
+        let amount_out = *amounts_out
+            .last()
+            .ok_or_else(|| anyhow::anyhow!("getAmountsOut returned no amounts"))?;
+
+        Ok(amount_out)
+    }
+
+    /// What buying `token` for `amount_in` WETH (or selling `amount_in` of it) is quoted at,
+    /// before slippage and taxes. Quotes don't move tokens, so no tax is taken from them.
+    pub async fn quote_swap(
+        &self,
+        token: &Erc20Token,
+        amount_in: U256,
+        buying: bool,
+    ) -> Result<U256> {
+        let weth_address: Address = CONTRACT.get_address().weth;
+
+        if token.dex == Dex::UniswapV2 {
+            return self
+                .get_v2_quote(amount_in, v2_path(weth_address, token, buying))
+                .await;
+        }
+
+        let quoter_address: Address = CONTRACT.get_address().uniswap_quoter;
+        let quoter = UNISWAP_QUOTER::new(quoter_address, self.client.clone());
+
+        let (token_in, token_out) = if buying {
+            (weth_address, token.address)
+        } else {
+            (token.address, weth_address)
+        };
+
+        let amount_out = if token.quote_token == weth_address {
+            let params = QuoteExactInputSingleParams {
+                token_in,
+                token_out,
+                amount_in,
+                fee: token.fee,
+                sqrt_price_limit_x96: U256::from(0),
+            };
+            quoter.quote_exact_input_single(params).call().await?.0
+        } else {
+            let quote_fee = quote_weth_pool_fee(token)?;
+            let path = if buying {
+                encode_v3_path(
+                    &[weth_address, token.quote_token, token.address],
+                    &[quote_fee, token.fee],
+                )
+            } else {
+                encode_v3_path(
+                    &[token.address, token.quote_token, weth_address],
+                    &[token.fee, quote_fee],
+                )
+            };
+            quoter.quote_exact_input(path, amount_in).call().await?.0
+        };
+
+        Ok(amount_out)
+    }
+
+    /// Transfers `amount` of `token` to the second anvil account and returns what arrived
+    pub async fn simulate_wallet_transfer(&self, token: &Erc20Token, amount: U256) -> Result<U256> {
+        let recipient = self.anvil.addresses()[1];
+        let token_contract = ERC20::new(token.address, self.client.clone());
+
+        let balance_before = token_contract.balance_of(recipient).call().await?;
+        token_contract
+            .transfer(recipient, amount)
+            .send()
+            .await?
+            .await?;
+        let balance_after = token_contract.balance_of(recipient).call().await?;
+
+        Ok(balance_after.saturating_sub(balance_before))
+    }
+
+    async fn get_deadline(&self) -> anyhow::Result<U256> {
+        let block = self
+            .client
+            .get_block(BlockNumber::Latest)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("could not fetch latest block"))?;
+
+        Ok(block.timestamp + U256::from(300))
+    }
+
     async fn get_amount_out_plus_gas_used(
         &self,
         token_in: Address,
         token_out: Address,
         amount_in: U256,
         fee: u32,
+        tax_bps: u32,
     ) -> anyhow::Result<(U256, U256)> {
-        let quoter_address: Address = CONTRACT.get_address().uniswap_quoter.parse()?;
+        let quoter_address: Address = CONTRACT.get_address().uniswap_quoter;
         let quoter = UNISWAP_QUOTER::new(quoter_address, self.client.clone());
 
         let params = QuoteExactInputSingleParams {
@@ -377,8 +858,27 @@ impl AnvilSimulator {
 
         let (amount_out, _, _, gas_used) = quoter.quote_exact_input_single(params).call().await?;
 
-        // reduce by 2% to account for token volatility
-        let amount_out = amount_out * U256::from(98) / U256::from(100);
+        // leave room for the strategy's slippage to account for token volatility,
+        // and for the token's measured tax
+        let amount_out = after_tax(self.strategy.current().min_amount_out(amount_out), tax_bps);
+
+        Ok((amount_out, gas_used))
+    }
+
+    async fn get_multihop_amount_out_plus_gas_used(
+        &self,
+        path: Bytes,
+        amount_in: U256,
+        tax_bps: u32,
+    ) -> anyhow::Result<(U256, U256)> {
+        let quoter_address: Address = CONTRACT.get_address().uniswap_quoter;
+        let quoter = UNISWAP_QUOTER::new(quoter_address, self.client.clone());
+
+        let (amount_out, _, _, gas_used) = quoter.quote_exact_input(path, amount_in).call().await?;
+
+        // leave room for the strategy's slippage to account for token volatility,
+        // and for the token's measured tax
+        let amount_out = after_tax(self.strategy.current().min_amount_out(amount_out), tax_bps);
 
         Ok((amount_out, gas_used))
     }
@@ -435,9 +935,9 @@ impl AnvilSimulator {
         &self,
         token: &Erc20Token,
     ) -> anyhow::Result<()> {
-        let factory_address: Address = CONTRACT.get_address().uniswap_factory.parse()?;
-        let swap_router_address: Address = CONTRACT.get_address().uniswap_swap_router.parse()?;
-        let weth_address: Address = CONTRACT.get_address().weth.parse()?;
+        let factory_address: Address = CONTRACT.get_address().uniswap_factory;
+        let swap_router_address: Address = CONTRACT.get_address().uniswap_swap_router;
+        let weth_address: Address = CONTRACT.get_address().weth;
         let weth_contract = ERC20::new(weth_address, self.client.clone());
 
         let weth_balance = weth_contract.balance_of(self.from_address).call().await?;
@@ -485,14 +985,25 @@ impl AnvilSimulator {
         let token_balance = format_units(new_token_balance_u256, u32::from(token.decimals))?;
 
         println!(
-            "YOU HAVE {} of {}, ({})",
-            token_balance, token.name, token.symbol
+            "[{}] YOU HAVE {} of {}, ({})",
+            self.chain, token_balance, token.name, token.symbol
         );
         Ok(new_token_balance_u256)
     }
 
+    pub async fn get_token_balance_by_address(
+        &self,
+        token_address: Address,
+    ) -> anyhow::Result<U256> {
+        let token_contract = ERC20::new(token_address, self.client.clone());
+
+        let token_balance = token_contract.balance_of(self.from_address).call().await?;
+
+        Ok(token_balance)
+    }
+
     async fn get_current_profit_loss(&self) -> anyhow::Result<()> {
-        let weth_address: Address = CONTRACT.get_address().weth.parse()?;
+        let weth_address: Address = CONTRACT.get_address().weth;
         let eth_balance = self.client.get_balance(self.from_address, None).await?;
         let weth = ERC20::new(weth_address, self.client.clone());
 
@@ -502,34 +1013,128 @@ impl AnvilSimulator {
 
         let profit = total_balance - STARTING_BALANCE;
 
-        println!("CURRENT PROFIT IS {}", profit);
+        println!("[{}] CURRENT PROFIT IS {}", self.chain, profit);
+
+        Ok(())
+    }
+
+    /// Takes an evm_snapshot of the fork, everything sent after it is undone by
+    /// `revert_to_snapshot`
+    pub async fn snapshot(&self) -> Result<U256> {
+        let snapshot_id = self
+            .client
+            .provider()
+            .request::<_, U256>("evm_snapshot", ())
+            .await?;
+
+        Ok(snapshot_id)
+    }
+
+    /// puts the fork back to `snapshot_id`, which can't be used again afterwards
+    pub async fn revert_to_snapshot(&self, snapshot_id: U256) -> Result<()> {
+        let reverted = self
+            .client
+            .provider()
+            .request::<_, bool>("evm_revert", [snapshot_id])
+            .await?;
+
+        if !reverted {
+            return Err(anyhow::anyhow!(
+                "[{}] fork snapshot {} could not be reverted",
+                self.chain,
+                snapshot_id
+            ));
+        }
 
         Ok(())
     }
 
+    /// ETH plus WETH held by the anvil account
+    pub async fn get_account_value(&self) -> Result<U256> {
+        let weth_address: Address = CONTRACT.get_address().weth;
+        let weth = ERC20::new(weth_address, self.client.clone());
+
+        let eth_balance = self.client.get_balance(self.from_address, None).await?;
+        let weth_balance = weth.balance_of(self.from_address).call().await?;
+
+        Ok(eth_balance + weth_balance)
+    }
+
+    /// ETH the anvil account paid for gas in the blocks after `block_number`
+    pub async fn gas_spent_since(&self, block_number: u64) -> Result<U256> {
+        let latest = self.client.get_block_number().await?.as_u64();
+
+        let mut gas_spent = U256::zero();
+        for block in block_number + 1..=latest {
+            let receipts = self.client.get_block_receipts(block).await?;
+            for receipt in receipts.iter().filter(|r| r.from == self.from_address) {
+                gas_spent += receipt.gas_used.unwrap_or_default()
+                    * receipt.effective_gas_price.unwrap_or_default();
+            }
+        }
+
+        Ok(gas_spent)
+    }
+
     async fn get_eth_balance(&self) -> anyhow::Result<U256> {
         // get account balance to see how much of new token recieved
 
         let new_eth_balance_u256 = self.client.get_balance(self.from_address, None).await?;
         let eth_balance = format_units(new_eth_balance_u256, 18u32)?;
 
-        println!("YOU HAVE {} of ETH", eth_balance);
+        println!("[{}] YOU HAVE {} of ETH", self.chain, eth_balance);
         Ok(new_eth_balance_u256)
     }
 
     async fn get_weth_balance(&self) -> anyhow::Result<U256> {
-        let weth_address: Address = CONTRACT.get_address().weth.parse()?;
+        let weth_address: Address = CONTRACT.get_address().weth;
         // get account balance to see how much of new token recieved
         let token_contract = ERC20::new(weth_address, self.client.clone());
 
         let new_token_balance_u256 = token_contract.balance_of(self.from_address).call().await?;
         let token_balance = format_units(new_token_balance_u256, u32::from(18u32))?;
 
-        println!("YOU HAVE {} of WETH", token_balance);
+        println!("[{}] YOU HAVE {} of WETH", self.chain, token_balance);
         Ok(new_token_balance_u256)
     }
 }
 
+/// encodes a uniswap v3 swap path, token (20 bytes) and fee (3 bytes) alternating
+pub fn encode_v3_path(tokens: &[Address], fees: &[u32]) -> Bytes {
+    let mut path: Vec<u8> = Vec::with_capacity(tokens.len() * 20 + fees.len() * 3);
+
+    for (index, token) in tokens.iter().enumerate() {
+        path.extend_from_slice(token.as_bytes());
+        if let Some(fee) = fees.get(index) {
+            path.extend_from_slice(&fee.to_be_bytes()[1..]);
+        }
+    }
+
+    Bytes::from(path)
+}
+
+/// v2 router path from WETH to the token (or back), hopping through its quote token
+fn v2_path(weth_address: Address, token: &Erc20Token, buying: bool) -> Vec<Address> {
+    let mut path = vec![weth_address];
+    if token.quote_token != weth_address {
+        path.push(token.quote_token);
+    }
+    path.push(token.address);
+
+    if !buying {
+        path.reverse();
+    }
+    path
+}
+
+fn quote_weth_pool_fee(token: &Erc20Token) -> Result<u32> {
+    CONTRACT
+        .get_address()
+        .quote_token(token.quote_token)
+        .map(|quote_token| quote_token.weth_pool_fee)
+        .ok_or_else(|| anyhow::anyhow!("{:?} is not a configured quote token", token.quote_token))
+}
+
 fn find_revert(trace: &CallFrame) -> Option<&CallFrame> {
     // If this call frame has an error, and no further nested calls, it's the revert point
     if trace.error.is_some() && (trace.calls.is_none() || trace.calls.as_ref().unwrap().is_empty())
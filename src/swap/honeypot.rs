@@ -0,0 +1,153 @@
+use crate::data::tokens::Erc20Token;
+use crate::swap::anvil_simlator::AnvilSimulator;
+use crate::swap::transfer_tax::{tax_bps, TokenTaxes};
+use anyhow::Result;
+use ethers::providers::Middleware;
+use ethers::types::U256;
+use ethers::utils::format_ether;
+use log::{info, warn};
+
+// share of the test buy sent to another wallet to measure the transfer tax
+const TRANSFER_SHARE: u64 = 10;
+
+/// A test buy sold straight back. Amounts are WETH (ETH on v2), gas is left out so
+/// only what the token and pool take shows up as loss.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RoundTrip {
+    /// WETH paid for the tokens that were sold back
+    pub spent: U256,
+    pub returned: U256,
+    /// tokens the sell was sent for
+    pub tokens_sold: U256,
+    /// tokens the sell did not get rid of
+    pub tokens_left: U256,
+    /// why the sell could not be sent at all
+    pub sell_error: Option<String>,
+    pub taxes: TokenTaxes,
+}
+
+impl RoundTrip {
+    /// share of `spent` that did not come back, 10_000 is everything
+    pub fn loss_bps(&self) -> u32 {
+        tax_bps(self.spent, self.returned)
+    }
+
+    /// why the token should not be bought, `None` when it sold back within `max_loss_bps`
+    pub fn rejection_reason(&self, max_loss_bps: u32) -> Option<String> {
+        if let Some(error) = &self.sell_error {
+            return Some(format!("honeypot, test sell failed => {}", error));
+        }
+
+        if self.tokens_left == self.tokens_sold {
+            return Some("honeypot, test sell reverted".to_string());
+        }
+
+        if !self.tokens_left.is_zero() {
+            return Some(format!(
+                "honeypot, test sell left {} of {} tokens",
+                self.tokens_left, self.tokens_sold
+            ));
+        }
+
+        let loss_bps = self.loss_bps();
+        if loss_bps > max_loss_bps {
+            return Some(format!(
+                "round trip lost {} bps, more than {} bps (taxes buy {} sell {} transfer {})",
+                loss_bps,
+                max_loss_bps,
+                self.taxes.buy_bps,
+                self.taxes.sell_bps,
+                self.taxes.transfer_bps
+            ));
+        }
+
+        None
+    }
+}
+
+/// Buys the strategy's honeypot test amount of `token` on an evm_snapshot of the fork,
+/// sends a tenth to another wallet, sells the rest back and reverts the snapshot, so the
+/// account is left as it was. Buy, transfer and sell taxes are measured on the way.
+/// `None` when the test buy got no tokens, e.g. because trading is not open yet.
+pub async fn simulate_round_trip(
+    token: &Erc20Token,
+    anvil: &AnvilSimulator,
+) -> Result<Option<RoundTrip>> {
+    let amount_in = anvil.strategy.current().honeypot_test_amount;
+
+    let snapshot_id = anvil.snapshot().await?;
+    let round_trip = buy_and_sell_back(token, anvil, amount_in).await;
+    anvil.revert_to_snapshot(snapshot_id).await?;
+
+    let round_trip = round_trip?;
+    if let Some(round_trip) = &round_trip {
+        info!(
+            "[{}] round trip of {} spent {} got back {}, {:?}",
+            anvil.chain,
+            token.name,
+            format_ether(round_trip.spent),
+            format_ether(round_trip.returned),
+            round_trip.taxes
+        );
+    }
+
+    Ok(round_trip)
+}
+
+async fn buy_and_sell_back(
+    token: &Erc20Token,
+    anvil: &AnvilSimulator,
+    amount_in: U256,
+) -> Result<Option<RoundTrip>> {
+    // the test trades accept any output, what they get is what gets measured
+    let test_token = Erc20Token {
+        taxes: Some(TokenTaxes::UNKNOWN),
+        ..token.clone()
+    };
+
+    let quoted_tokens = anvil.quote_swap(token, amount_in, true).await?;
+    let tokens_bought = anvil
+        .simulate_buying_token_with_amount(&test_token, amount_in)
+        .await?;
+    if tokens_bought.is_zero() {
+        return Ok(None);
+    }
+
+    let transferred = tokens_bought / U256::from(TRANSFER_SHARE);
+    let transfer_bps = match anvil.simulate_wallet_transfer(token, transferred).await {
+        Ok(received) => tax_bps(transferred, received),
+        Err(error) => {
+            warn!("test transfer of {} failed => {}", token.name, error);
+            TokenTaxes::UNKNOWN.transfer_bps
+        }
+    };
+
+    let tokens_sold = anvil.get_token_balance_by_address(token.address).await?;
+    let quoted_return = anvil.quote_swap(token, tokens_sold, false).await?;
+
+    let start_block = anvil.client.get_block_number().await?.as_u64();
+    let value_before = anvil.get_account_value().await?;
+    let (tokens_left, sell_error) = match anvil.simulate_selling_token_for_weth(&test_token).await {
+        Ok(tokens_left) => (tokens_left, None),
+        Err(error) => (tokens_sold, Some(error.to_string())),
+    };
+
+    // what came back is the value gained plus the gas paid for the sell
+    let value_after = anvil.get_account_value().await?;
+    let gas_spent = anvil.gas_spent_since(start_block).await?;
+    let returned = (value_after + gas_spent).saturating_sub(value_before);
+
+    Ok(Some(RoundTrip {
+        // only what was paid for the tokens that were sold back
+        spent: amount_in * tokens_sold / tokens_bought,
+        returned,
+        tokens_sold,
+        tokens_left,
+        sell_error,
+        taxes: TokenTaxes {
+            buy_bps: tax_bps(quoted_tokens, tokens_bought),
+            sell_bps: tax_bps(quoted_return, returned),
+            transfer_bps,
+        },
+    }))
+}
@@ -0,0 +1,135 @@
+use crate::data::tokens::Erc20Token;
+use crate::multicall::{CallHandle, MulticallResults};
+use crate::swap::token_price::get_quote_price_in_weth;
+use crate::utils::type_conversion::u256_to_f64_with_decimals;
+use anyhow::Result;
+use ethers::providers::{Provider, Ws};
+use ethers::types::U256;
+use log::debug;
+use std::sync::Arc;
+
+/// Allowed range for the price a v3 pool is initialized at, any bound can be left unset.
+/// Set per strategy profile, see `Strategy::launch_price_bounds`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LaunchPriceBounds {
+    pub min_price_weth: Option<f64>,
+    pub max_price_weth: Option<f64>,
+    pub min_fdv_weth: Option<f64>,
+    pub max_fdv_weth: Option<f64>,
+}
+
+impl LaunchPriceBounds {
+    /// returns why a launch at `price_weth` / `fdv_weth` is rejected, None if it is allowed
+    pub fn rejection_reason(&self, price_weth: f64, fdv_weth: f64) -> Option<String> {
+        if !price_weth.is_finite() || !fdv_weth.is_finite() {
+            return Some(format!(
+                "launch price {} WETH / fdv {} WETH is not a finite number",
+                price_weth, fdv_weth
+            ));
+        }
+
+        let checks = [
+            (
+                "price",
+                price_weth,
+                self.min_price_weth,
+                self.max_price_weth,
+            ),
+            ("fdv", fdv_weth, self.min_fdv_weth, self.max_fdv_weth),
+        ];
+
+        for (name, value, min, max) in checks {
+            if let Some(min) = min.filter(|min| value < *min) {
+                return Some(format!("launch {} {} WETH below {}", name, value, min));
+            }
+            if let Some(max) = max.filter(|max| value > *max) {
+                return Some(format!("launch {} {} WETH above {}", name, value, max));
+            }
+        }
+
+        None
+    }
+
+    /// checks the launch price recorded on `token`, None if allowed or not initialized yet.
+    /// `quote_price_weth` is the WETH price of one whole quote token
+    pub fn token_rejection_reason(
+        &self,
+        token: &Erc20Token,
+        quote_price_weth: f64,
+    ) -> Result<Option<String>> {
+        if token.launch_sqrt_price_x96.is_zero() {
+            return Ok(None);
+        }
+
+        let price_in_quote = sqrt_price_x96_to_token_price(
+            token.launch_sqrt_price_x96,
+            token.is_token_0,
+            token.decimals,
+            token.quote_decimals,
+        )?;
+        let price_weth = price_in_quote * quote_price_weth;
+        let fdv_weth = fully_diluted_valuation(price_weth, token.total_supply, token.decimals)?;
+
+        Ok(self.rejection_reason(price_weth, fdv_weth))
+    }
+}
+
+/// checks the launch price of `token` against the strategy's `bounds`
+pub async fn launch_rejection_reason(
+    token: &Erc20Token,
+    bounds: &LaunchPriceBounds,
+    client: &Arc<Provider<Ws>>,
+) -> Result<Option<String>> {
+    if token.launch_sqrt_price_x96.is_zero() {
+        return Ok(None);
+    }
+
+    let quote_price_weth = get_quote_price_in_weth(token.quote_token, client).await?;
+
+    bounds.token_rejection_reason(token, quote_price_weth)
+}
+
+/// what `slot0()` of a v3 pool returns
+pub type Slot0 = (U256, i32, u16, u16, u16, u8, bool);
+
+/// sqrtPriceX96 and tick a v3 pool was initialized at, `(0, 0)` when it isn't yet. A pool of a
+/// pending createPool has no code, so slot0 can't be read, the Initialize log records it later.
+pub fn read_launch_price(results: &MulticallResults, slot_0: &CallHandle<Slot0>) -> (U256, i32) {
+    match results.get(slot_0) {
+        Ok((sqrt_price_x96, tick, _, _, _, _, _)) => (sqrt_price_x96, tick),
+        Err(error) => {
+            debug!("pool not initialized yet => {}", error);
+            (U256::zero(), 0)
+        }
+    }
+}
+
+/// price of one whole token in its quote token
+pub fn sqrt_price_x96_to_token_price(
+    sqrt_price_x96: U256,
+    is_token_0: bool,
+    token_decimals: u8,
+    quote_decimals: u8,
+) -> Result<f64> {
+    // launch prices can be extreme, so go through a string instead of u128
+    let sqrt_price = u256_to_f64_with_decimals(sqrt_price_x96, 0)?;
+    // raw token1 per raw token0
+    let raw_price = (sqrt_price / f64::powi(2.0, 96)).powi(2);
+    let decimals_adjustment = f64::powi(10.0, token_decimals as i32 - quote_decimals as i32);
+
+    let price = if is_token_0 {
+        // token1 is the quote token
+        raw_price * decimals_adjustment
+    } else {
+        // token0 is the quote token
+        decimals_adjustment / raw_price
+    };
+
+    Ok(price)
+}
+
+pub fn fully_diluted_valuation(price_weth: f64, total_supply: U256, decimals: u8) -> Result<f64> {
+    let total_supply = u256_to_f64_with_decimals(total_supply, decimals as u32)?;
+
+    Ok(price_weth * total_supply)
+}
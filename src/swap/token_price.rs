@@ -1,65 +1,220 @@
 use ethers::abi::Address;
 use ethers::providers::{Provider, Ws};
+use ethers::types::U256;
 use std::sync::Arc;
 
-use crate::abi::erc20::ERC20;
+use crate::abi::uniswap_pair::UNISWAP_PAIR;
 use crate::abi::uniswap_pool::UNISWAP_V3_POOL;
 use crate::data::contracts::CONTRACT;
-use crate::data::tokens::Erc20Token;
+use crate::data::tokens::{Dex, Erc20Token};
+use crate::mempool::{compute_v3_pool_address, sort_tokens};
+use crate::multicall::{CallHandle, Multicall};
+use crate::swap::launch_price::sqrt_price_x96_to_token_price;
 
+/// price of one whole token in WETH, converted through the quote token if needed.
+/// The token's pool and the quote token's WETH pool are read in one eth_call.
 pub async fn get_token_price(
     token: &Erc20Token,
     client: &Arc<Provider<Ws>>,
 ) -> anyhow::Result<f64> {
+    let quote_pool = quote_weth_pool(token.quote_token)?;
+
+    let mut multicall = Multicall::new(client);
     let pool = UNISWAP_V3_POOL::new(token.pool_address, client.clone());
-    let weth_address: Address = CONTRACT.get_address().weth.parse()?;
+    let pair = UNISWAP_PAIR::new(token.pool_address, client.clone());
+    let (slot_0, reserves) = if token.dex == Dex::UniswapV2 {
+        (None, Some(multicall.add(pair.get_reserves())))
+    } else {
+        (Some(multicall.add(pool.slot_0())), None)
+    };
+    let quote_slot_0 = quote_pool.as_ref().map(|quote_pool| {
+        let pool = UNISWAP_V3_POOL::new(quote_pool.address, client.clone());
+        multicall.add(pool.slot_0())
+    });
+    let results = multicall.call().await?;
 
-    // Call slot0 on the pool
-    let (sqrt_price_x96, _, _, _, _, _, _) = pool.slot_0().call().await?;
+    let price_in_quote = match (slot_0, reserves) {
+        (Some(slot_0), _) => {
+            let (sqrt_price_x96, _, _, _, _, _, _) = results.get(&slot_0)?;
 
-    // Determine which token is WETH and which is your target token
-    let (token0_address, token1_address) = if token.is_token_0 {
-        (token.address, weth_address)
-    } else {
-        (weth_address, token.address)
+            sqrt_price_x96_to_token_price(
+                sqrt_price_x96,
+                token.is_token_0,
+                token.decimals,
+                token.quote_decimals,
+            )?
+        }
+        (None, Some(reserves)) => {
+            let (reserve_0, reserve_1, _) = results.get(&reserves)?;
+            v2_token_price(token, reserve_0, reserve_1)
+        }
+        (None, None) => unreachable!("every token has a pool or a pair"),
+    };
+
+    let quote_price = match (quote_pool, quote_slot_0) {
+        (Some(quote_pool), Some(quote_slot_0)) => {
+            let (sqrt_price_x96, _, _, _, _, _, _) = results.get(&quote_slot_0)?;
+            quote_pool.price_in_weth(sqrt_price_x96)?
+        }
+        _ => 1.0,
+    };
+
+    Ok(price_in_quote * quote_price)
+}
+
+/// price of one whole quote token in WETH, read from its v3 pool with WETH
+pub async fn get_quote_price_in_weth(
+    quote_token: Address,
+    client: &Arc<Provider<Ws>>,
+) -> anyhow::Result<f64> {
+    let quote_pool = match quote_weth_pool(quote_token)? {
+        Some(quote_pool) => quote_pool,
+        None => return Ok(1.0),
     };
 
-    // Fetch token decimals if needed:
-    let token0_contract = ERC20::new(token0_address, client.clone());
-    let token1_contract = ERC20::new(token1_address, client.clone());
-    let decimals0 = token0_contract.decimals().call().await?;
-    let decimals1 = token1_contract.decimals().call().await?;
-
-    // Check which is WETH and compute price
-    let sqrt_price_f64 = sqrt_price_x96.as_u128() as f64;
-    let base = f64::powi(2.0, 192);
-
-    let price = if !token.is_token_0 {
-        // price = (p^2) / 2^192
-        let raw_price = (sqrt_price_f64 * sqrt_price_f64) / base;
-        // Adjust for decimals if needed:
-        // token1 in WETH
-        // (If token1 has `d1` decimals and WETH has `d0` = 18)
-        // price_per_token_in_weth = raw_price * 10^(d1 - d0)
-        raw_price * f64::powi(10.0, decimals1 as i32 - 18)
+    let pool = UNISWAP_V3_POOL::new(quote_pool.address, client.clone());
+    let (sqrt_price_x96, _, _, _, _, _, _) = pool.slot_0().call().await?;
+
+    quote_pool.price_in_weth(sqrt_price_x96)
+}
+
+/// the v3 pool pricing a quote token in WETH
+struct QuoteWethPool {
+    address: Address,
+    quote_is_token_0: bool,
+    quote_decimals: u8,
+}
+
+impl QuoteWethPool {
+    fn price_in_weth(&self, sqrt_price_x96: U256) -> anyhow::Result<f64> {
+        sqrt_price_x96_to_token_price(
+            sqrt_price_x96,
+            self.quote_is_token_0,
+            self.quote_decimals,
+            18,
+        )
+    }
+}
+
+/// None for WETH itself, which needs no conversion
+fn quote_weth_pool(quote_token: Address) -> anyhow::Result<Option<QuoteWethPool>> {
+    let addresses = CONTRACT.get_address();
+    let weth_address = addresses.weth;
+
+    if quote_token == weth_address {
+        return Ok(None);
+    }
+
+    let quote = addresses
+        .quote_token(quote_token)
+        .ok_or_else(|| anyhow::anyhow!("{:?} is not a configured quote token", quote_token))?;
+
+    let factory_address = addresses.uniswap_factory;
+    let (token0, token1) = sort_tokens(quote.address, weth_address);
+
+    Ok(Some(QuoteWethPool {
+        address: compute_v3_pool_address(factory_address, token0, token1, quote.weth_pool_fee),
+        quote_is_token_0: quote.address == token0,
+        quote_decimals: quote.decimals,
+    }))
+}
+
+/// price of one whole token in the quote token from the pair reserves
+fn v2_token_price(token: &Erc20Token, reserve_0: u128, reserve_1: u128) -> f64 {
+    let (token_reserve, quote_reserve) = if token.is_token_0 {
+        (reserve_0, reserve_1)
     } else {
-        // token1 is WETH
-        // price(token0 in WETH) = 2^192 / (p^2)
-        let raw_price = base / (sqrt_price_f64 * sqrt_price_f64);
-        // Adjust for decimals:
-        raw_price * f64::powi(10.0, decimals0 as i32 - 18)
+        (reserve_1, reserve_0)
     };
 
-    Ok(price)
+    if token_reserve == 0 {
+        return 0.0;
+    }
+
+    // price of one whole token in the quote token
+    // = (quote / 10^quote decimals) / (token / 10^decimals)
+    let raw_price = quote_reserve as f64 / token_reserve as f64;
+    raw_price * f64::powi(10.0, token.decimals as i32 - token.quote_decimals as i32)
 }
 
-pub async fn get_token_weth_liquidity(
+pub async fn get_token_quote_liquidity(
     token: &Erc20Token,
     client: &Arc<Provider<Ws>>,
 ) -> anyhow::Result<u128> {
+    // v2 pairs have no concentrated liquidity, so the quote reserve stands in for it
+    if token.dex == Dex::UniswapV2 {
+        let (_, quote_reserve) = get_v2_reserves(token, client).await?;
+        return Ok(quote_reserve);
+    }
+
     let pool = UNISWAP_V3_POOL::new(token.pool_address, client.clone());
 
     let liquidity = pool.liquidity().call().await?;
 
     Ok(liquidity)
 }
+
+/// Liquidity of every token's pool (quote reserve for v2 pairs) read in one eth_call,
+/// in the order of `tokens`. A pool that can't be read only fails its own entry.
+pub async fn get_quote_liquidities(
+    tokens: &[Erc20Token],
+    client: &Arc<Provider<Ws>>,
+) -> anyhow::Result<Vec<anyhow::Result<u128>>> {
+    enum Liquidity {
+        V3(CallHandle<u128>),
+        V2(CallHandle<(u128, u128, u32)>),
+    }
+
+    let mut multicall = Multicall::new(client);
+    let handles: Vec<Liquidity> = tokens
+        .iter()
+        .map(|token| {
+            if token.dex == Dex::UniswapV2 {
+                let pair = UNISWAP_PAIR::new(token.pool_address, client.clone());
+                Liquidity::V2(multicall.add(pair.get_reserves()))
+            } else {
+                let pool = UNISWAP_V3_POOL::new(token.pool_address, client.clone());
+                Liquidity::V3(multicall.add(pool.liquidity()))
+            }
+        })
+        .collect();
+
+    if multicall.is_empty() {
+        return Ok(vec![]);
+    }
+    let results = multicall.call().await?;
+
+    let liquidities = tokens
+        .iter()
+        .zip(handles)
+        .map(|(token, handle)| match handle {
+            Liquidity::V3(liquidity) => results.get(&liquidity),
+            Liquidity::V2(reserves) => {
+                let (reserve_0, reserve_1, _) = results.get(&reserves)?;
+                Ok(if token.is_token_0 {
+                    reserve_1
+                } else {
+                    reserve_0
+                })
+            }
+        })
+        .collect();
+
+    Ok(liquidities)
+}
+
+/// returns (token reserve, quote reserve) of a v2 pair
+async fn get_v2_reserves(
+    token: &Erc20Token,
+    client: &Arc<Provider<Ws>>,
+) -> anyhow::Result<(u128, u128)> {
+    let pair = UNISWAP_PAIR::new(token.pool_address, client.clone());
+
+    let (reserve_0, reserve_1, _) = pair.get_reserves().call().await?;
+
+    if token.is_token_0 {
+        Ok((reserve_0, reserve_1))
+    } else {
+        Ok((reserve_1, reserve_0))
+    }
+}
@@ -0,0 +1,41 @@
+use ethers::types::U256;
+use serde::{Deserialize, Serialize};
+
+pub const BASIS_POINTS: u32 = 10_000;
+
+/// Taxes a token took on the test trades, in basis points of the amount moved.
+/// Launch tokens often charge buys, sells and plain transfers differently.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenTaxes {
+    /// tokens received short of the quote
+    pub buy_bps: u32,
+    /// WETH (ETH on v2) received short of the quote
+    pub sell_bps: u32,
+    /// tokens a wallet-to-wallet transfer did not deliver
+    pub transfer_bps: u32,
+}
+
+impl TokenTaxes {
+    /// everything taxed, the test trades accept any output while the real taxes are measured
+    pub const UNKNOWN: TokenTaxes = TokenTaxes {
+        buy_bps: BASIS_POINTS,
+        sell_bps: BASIS_POINTS,
+        transfer_bps: BASIS_POINTS,
+    };
+}
+
+/// share of `expected` that did not arrive, 0 when at least `expected` did
+pub fn tax_bps(expected: U256, received: U256) -> u32 {
+    if expected.is_zero() || received >= expected {
+        return 0;
+    }
+
+    ((expected - received) * U256::from(BASIS_POINTS) / expected).as_u32()
+}
+
+/// what is left of `amount` after a `tax_bps` tax
+pub fn after_tax(amount: U256, tax_bps: u32) -> U256 {
+    let tax_bps = tax_bps.min(BASIS_POINTS);
+
+    amount * U256::from(BASIS_POINTS - tax_bps) / U256::from(BASIS_POINTS)
+}
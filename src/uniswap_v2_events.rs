@@ -0,0 +1,72 @@
+use crate::abi::uniswap_factory_v2::PairCreatedFilter;
+use crate::abi::uniswap_pair::MintFilter;
+use crate::data::contracts::CONTRACT;
+use crate::uniswap_v3_events::EventDecodeError;
+use ethers::abi::RawLog;
+use ethers::contract::EthEvent;
+use ethers::types::{Address, Filter, Log};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PairCreatedEvent {
+    pub token0: Address,
+    pub token1: Address,
+    pub pair: Address,
+}
+
+impl From<PairCreatedFilter> for PairCreatedEvent {
+    fn from(event: PairCreatedFilter) -> Self {
+        Self {
+            token0: event.token_0,
+            token1: event.token_1,
+            pair: event.pair,
+        }
+    }
+}
+
+/// filter for PairCreated events on the uniswap v2 factory
+pub fn set_pair_created_filter() -> anyhow::Result<Filter> {
+    let factory_address = CONTRACT.get_address().uniswap_v2_factory_address()?;
+
+    let filter = Filter::new()
+        .address(factory_address)
+        .topic0(PairCreatedFilter::signature());
+
+    Ok(filter)
+}
+
+pub fn is_pair_created_log(log: &Log) -> bool {
+    log.topics.first() == Some(&PairCreatedFilter::signature())
+}
+
+/// filter for Mint events on the given v2 pairs, an empty list would match every pair
+pub fn set_mint_filter(pairs: Vec<Address>) -> Filter {
+    Filter::new().address(pairs).topic0(MintFilter::signature())
+}
+
+pub fn is_mint_log(log: &Log) -> bool {
+    log.topics.first() == Some(&MintFilter::signature())
+}
+
+pub fn decode_paircreated_event(log: &Log) -> Result<PairCreatedEvent, EventDecodeError> {
+    let signature = *log
+        .topics
+        .first()
+        .ok_or(EventDecodeError::MissingSignature)?;
+
+    if signature != PairCreatedFilter::signature() {
+        return Err(EventDecodeError::UnknownSignature(signature));
+    }
+
+    let raw_log = RawLog {
+        topics: log.topics.clone(),
+        data: log.data.to_vec(),
+    };
+
+    let event =
+        PairCreatedFilter::decode_log(&raw_log).map_err(|error| EventDecodeError::Malformed {
+            event: "PairCreated",
+            reason: error.to_string(),
+        })?;
+
+    Ok(event.into())
+}
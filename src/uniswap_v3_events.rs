@@ -0,0 +1,232 @@
+use crate::abi::uniswap_pool::{
+    BurnFilter, CollectFilter, FlashFilter, InitializeFilter, MintFilter, SwapFilter,
+};
+use crate::abi::uniswap_v3_factory::PoolCreatedFilter;
+use crate::data::contracts::CONTRACT;
+use ethers::abi::RawLog;
+use ethers::contract::EthEvent;
+use ethers::types::{Address, Filter, Log, H256};
+use std::fmt;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PoolCreatedEvent {
+    pub token0: Address,
+    pub token1: Address,
+    pub fee: u32,
+    pub tick_spacing: i32,
+    pub pool: Address,
+}
+
+impl From<PoolCreatedFilter> for PoolCreatedEvent {
+    fn from(event: PoolCreatedFilter) -> Self {
+        Self {
+            token0: event.token_0,
+            token1: event.token_1,
+            fee: event.fee,
+            tick_spacing: event.tick_spacing,
+            pool: event.pool,
+        }
+    }
+}
+
+/// Every factory and pool event the bot understands, tagged with the contract that emitted it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UniswapEvent {
+    PoolCreated(PoolCreatedEvent),
+    Initialize {
+        pool: Address,
+        event: InitializeFilter,
+    },
+    Mint {
+        pool: Address,
+        event: MintFilter,
+    },
+    Burn {
+        pool: Address,
+        event: BurnFilter,
+    },
+    Swap {
+        pool: Address,
+        event: SwapFilter,
+    },
+    Collect {
+        pool: Address,
+        event: CollectFilter,
+    },
+    Flash {
+        pool: Address,
+        event: FlashFilter,
+    },
+}
+
+impl UniswapEvent {
+    /// address of the contract that emitted the event (factory events report the new pool)
+    pub fn pool(&self) -> Address {
+        match self {
+            UniswapEvent::PoolCreated(event) => event.pool,
+            UniswapEvent::Initialize { pool, .. }
+            | UniswapEvent::Mint { pool, .. }
+            | UniswapEvent::Burn { pool, .. }
+            | UniswapEvent::Swap { pool, .. }
+            | UniswapEvent::Collect { pool, .. }
+            | UniswapEvent::Flash { pool, .. } => *pool,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            UniswapEvent::PoolCreated(_) => "PoolCreated",
+            UniswapEvent::Initialize { .. } => "Initialize",
+            UniswapEvent::Mint { .. } => "Mint",
+            UniswapEvent::Burn { .. } => "Burn",
+            UniswapEvent::Swap { .. } => "Swap",
+            UniswapEvent::Collect { .. } => "Collect",
+            UniswapEvent::Flash { .. } => "Flash",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EventDecodeError {
+    /// log has no topic0, e.g. an anonymous event
+    MissingSignature,
+    /// topic0 does not match any event in `UniswapEvent`
+    UnknownSignature(H256),
+    /// topic0 matched `event` but the topics / data could not be decoded
+    Malformed { event: &'static str, reason: String },
+    /// log decoded fine but was not the event the caller asked for
+    UnexpectedEvent {
+        expected: &'static str,
+        found: &'static str,
+    },
+}
+
+impl fmt::Display for EventDecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EventDecodeError::MissingSignature => write!(f, "log has no event signature topic"),
+            EventDecodeError::UnknownSignature(signature) => {
+                write!(f, "unknown event signature {:?}", signature)
+            }
+            EventDecodeError::Malformed { event, reason } => {
+                write!(f, "malformed {} log => {}", event, reason)
+            }
+            EventDecodeError::UnexpectedEvent { expected, found } => {
+                write!(f, "expected {} event but found {}", expected, found)
+            }
+        }
+    }
+}
+
+impl std::error::Error for EventDecodeError {}
+
+/// filter for PoolCreated events on the uniswap v3 factory
+pub fn set_signature_filter() -> anyhow::Result<Filter> {
+    let factory_address: Address = CONTRACT.get_address().uniswap_factory;
+
+    let filter = Filter::new()
+        .address(factory_address)
+        .topic0(PoolCreatedFilter::signature());
+
+    Ok(filter)
+}
+
+/// filter for Mint events on the given v3 pools, an empty list would match every pool
+pub fn set_mint_filter(pools: Vec<Address>) -> Filter {
+    Filter::new().address(pools).topic0(MintFilter::signature())
+}
+
+pub fn is_mint_log(log: &Log) -> bool {
+    log.topics.first() == Some(&MintFilter::signature())
+}
+
+/// filter for Initialize events on the given v3 pools, an empty list would match every pool
+pub fn set_initialize_filter(pools: Vec<Address>) -> Filter {
+    Filter::new()
+        .address(pools)
+        .topic0(InitializeFilter::signature())
+}
+
+pub fn is_initialize_log(log: &Log) -> bool {
+    log.topics.first() == Some(&InitializeFilter::signature())
+}
+
+/// filter for Initialize, Mint, Burn, Swap, Collect and Flash events on the given pools
+pub fn set_pool_events_filter(pools: Vec<Address>) -> Filter {
+    Filter::new().address(pools).topic0(vec![
+        InitializeFilter::signature(),
+        MintFilter::signature(),
+        BurnFilter::signature(),
+        SwapFilter::signature(),
+        CollectFilter::signature(),
+        FlashFilter::signature(),
+    ])
+}
+
+pub fn decode_uniswap_event(log: &Log) -> Result<UniswapEvent, EventDecodeError> {
+    let signature = *log
+        .topics
+        .first()
+        .ok_or(EventDecodeError::MissingSignature)?;
+    let pool = log.address;
+
+    let event = if signature == PoolCreatedFilter::signature() {
+        UniswapEvent::PoolCreated(decode_log::<PoolCreatedFilter>(log, "PoolCreated")?.into())
+    } else if signature == InitializeFilter::signature() {
+        UniswapEvent::Initialize {
+            pool,
+            event: decode_log(log, "Initialize")?,
+        }
+    } else if signature == MintFilter::signature() {
+        UniswapEvent::Mint {
+            pool,
+            event: decode_log(log, "Mint")?,
+        }
+    } else if signature == BurnFilter::signature() {
+        UniswapEvent::Burn {
+            pool,
+            event: decode_log(log, "Burn")?,
+        }
+    } else if signature == SwapFilter::signature() {
+        UniswapEvent::Swap {
+            pool,
+            event: decode_log(log, "Swap")?,
+        }
+    } else if signature == CollectFilter::signature() {
+        UniswapEvent::Collect {
+            pool,
+            event: decode_log(log, "Collect")?,
+        }
+    } else if signature == FlashFilter::signature() {
+        UniswapEvent::Flash {
+            pool,
+            event: decode_log(log, "Flash")?,
+        }
+    } else {
+        return Err(EventDecodeError::UnknownSignature(signature));
+    };
+
+    Ok(event)
+}
+
+pub fn decode_poolcreated_event(log: &Log) -> Result<PoolCreatedEvent, EventDecodeError> {
+    match decode_uniswap_event(log)? {
+        UniswapEvent::PoolCreated(event) => Ok(event),
+        other => Err(EventDecodeError::UnexpectedEvent {
+            expected: "PoolCreated",
+            found: other.name(),
+        }),
+    }
+}
+
+fn decode_log<T: EthEvent>(log: &Log, event: &'static str) -> Result<T, EventDecodeError> {
+    let raw_log = RawLog {
+        topics: log.topics.clone(),
+        data: log.data.to_vec(),
+    };
+
+    T::decode_log(&raw_log).map_err(|error| EventDecodeError::Malformed {
+        event,
+        reason: error.to_string(),
+    })
+}
@@ -1,3 +1,4 @@
+use crate::data::contracts::scoped_chain;
 use colored::*;
 use std::fs::File;
 
@@ -13,9 +14,13 @@ pub fn setup_logger() -> Result<(), fern::InitError> {
                 log::Level::Debug => "magenta",
                 log::Level::Trace => "bright black",
             };
+            let chain = scoped_chain()
+                .map(|chain| chain.to_string())
+                .unwrap_or_else(|| "-".to_string());
             out.finish(format_args!(
-                "{}[{}][{}] {}",
+                "{}[{}][{}][{}] {}",
                 chrono::Local::now().format("[%H:%M:%S]"),
+                chain,
                 record.target(),
                 record.level().to_string().color(color),
                 message
@@ -0,0 +1,119 @@
+use anyhow::{anyhow, Result};
+use ethers::signers::{LocalWallet, Signer};
+use ethers::types::Chain;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::process::{Command, Stdio};
+
+/// path of the encrypted JSON keystore holding the signing key
+pub const KEYSTORE_PATH_VAR: &str = "KEYSTORE_PATH";
+/// keystore password, prompted for when not set
+pub const KEYSTORE_PASSWORD_VAR: &str = "KEYSTORE_PASSWORD";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InterpolationError {
+    /// a `${` without its closing `}`
+    Unterminated,
+    /// `${}`
+    EmptyName,
+    /// every referenced variable that is not set
+    UnsetVars(Vec<String>),
+}
+
+impl fmt::Display for InterpolationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InterpolationError::Unterminated => write!(f, "${{ is never closed"),
+            InterpolationError::EmptyName => write!(f, "${{}} has no variable name"),
+            InterpolationError::UnsetVars(names) => {
+                write!(f, "environment variable(s) not set: {}", names.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for InterpolationError {}
+
+/// Replaces every `${VAR}` in `value` with the environment variable VAR, so api keys and
+/// other secrets can live in .env instead of the committed config
+pub fn interpolate_env(value: &str) -> Result<String, InterpolationError> {
+    let mut interpolated = String::with_capacity(value.len());
+    let mut unset_vars: Vec<String> = vec![];
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${") {
+        interpolated.push_str(&rest[..start]);
+
+        let after_open = &rest[start + 2..];
+        let end = after_open
+            .find('}')
+            .ok_or(InterpolationError::Unterminated)?;
+        let name = after_open[..end].trim();
+        if name.is_empty() {
+            return Err(InterpolationError::EmptyName);
+        }
+
+        match std::env::var(name) {
+            Ok(var) => interpolated.push_str(&var),
+            Err(_) => unset_vars.push(name.to_string()),
+        }
+        rest = &after_open[end + 1..];
+    }
+    interpolated.push_str(rest);
+
+    if !unset_vars.is_empty() {
+        return Err(InterpolationError::UnsetVars(unset_vars));
+    }
+
+    Ok(interpolated)
+}
+
+/// Decrypts the keystore at `path` into a wallet signing for `chain`
+pub fn load_keystore_wallet(path: &str, password: &str, chain: Chain) -> Result<LocalWallet> {
+    // the keystore error never contains the password or key, but say which file failed
+    let wallet = LocalWallet::decrypt_keystore(path, password)
+        .map_err(|error| anyhow!("could not decrypt keystore {} => {}", path, error))?;
+
+    Ok(wallet.with_chain_id(chain))
+}
+
+/// Signing wallet from the keystore at KEYSTORE_PATH, None when it is not set. The
+/// password comes from KEYSTORE_PASSWORD or is prompted for on the terminal.
+pub fn load_signer(chain: Chain) -> Result<Option<LocalWallet>> {
+    let path = match std::env::var(KEYSTORE_PATH_VAR) {
+        Ok(path) => path,
+        Err(_) => return Ok(None),
+    };
+
+    let password = match std::env::var(KEYSTORE_PASSWORD_VAR) {
+        Ok(password) => password,
+        Err(_) => prompt_password(&format!("password for keystore {}: ", path))?,
+    };
+
+    load_keystore_wallet(&path, &password, chain).map(Some)
+}
+
+/// reads a line from stdin with terminal echo turned off where stty is available
+fn prompt_password(prompt: &str) -> Result<String> {
+    eprint!("{}", prompt);
+    io::stderr().flush()?;
+
+    let echo_off = set_terminal_echo(false);
+    let mut password = String::new();
+    let read = io::stdin().lock().read_line(&mut password);
+    if echo_off {
+        set_terminal_echo(true);
+        eprintln!();
+    }
+    read?;
+
+    Ok(password.trim_end_matches(['\r', '\n']).to_string())
+}
+
+fn set_terminal_echo(on: bool) -> bool {
+    Command::new("stty")
+        .arg(if on { "echo" } else { "-echo" })
+        .stdin(Stdio::inherit())
+        .status()
+        .is_ok_and(|status| status.success())
+}
@@ -0,0 +1,20 @@
+use ethers::types::Address;
+use snipper::swap::anvil_simlator::encode_v3_path;
+
+#[test]
+fn test_encode_v3_path() {
+    let weth = Address::from_low_u64_be(1);
+    let usdc = Address::from_low_u64_be(2);
+    let token = Address::from_low_u64_be(3);
+
+    let path = encode_v3_path(&[weth, usdc, token], &[500, 10_000]);
+
+    assert_eq!(path.len(), 20 * 3 + 3 * 2);
+    assert_eq!(&path[0..20], weth.as_bytes());
+    // 500 = 0x0001f4
+    assert_eq!(&path[20..23], &[0x00, 0x01, 0xf4]);
+    assert_eq!(&path[23..43], usdc.as_bytes());
+    // 10000 = 0x002710
+    assert_eq!(&path[43..46], &[0x00, 0x27, 0x10]);
+    assert_eq!(&path[46..66], token.as_bytes());
+}
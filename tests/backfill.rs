@@ -0,0 +1,21 @@
+use snipper::backfill::{is_range_too_large_error, page_end};
+
+#[test]
+fn test_page_end_is_clamped_to_range() {
+    assert_eq!(page_end(100, 50, 1_000), 149);
+    assert_eq!(page_end(990, 50, 1_000), 1_000);
+    assert_eq!(page_end(100, 1, 1_000), 100);
+    assert_eq!(page_end(100, 0, 1_000), 100);
+}
+
+#[test]
+fn test_detects_range_too_large_errors() {
+    assert!(is_range_too_large_error(
+        "(code: -32005, message: query returned more than 10000 results)"
+    ));
+    assert!(is_range_too_large_error(
+        "Log response size exceeded. You can make eth_getLogs requests with up to a 2K block range"
+    ));
+    assert!(is_range_too_large_error("block range is too wide"));
+    assert!(!is_range_too_large_error("connection reset by peer"));
+}
@@ -0,0 +1,101 @@
+use ethers::types::Chain;
+use snipper::data::config_watch::check_contracts_reload;
+use snipper::data::contracts::{parse_chain, ContractAddressMap};
+
+#[test]
+fn test_parse_chain_by_name_or_id() -> anyhow::Result<()> {
+    assert_eq!(parse_chain("mainnet")?, Chain::Mainnet);
+    assert_eq!(parse_chain("Base")?, Chain::Base);
+    assert_eq!(parse_chain("8453")?, Chain::Base);
+    assert_eq!(parse_chain(" 1 ")?, Chain::Mainnet);
+
+    assert!(parse_chain("not-a-chain").is_err());
+    assert!(parse_chain("12345678901").is_err());
+
+    Ok(())
+}
+
+const ARBITRUM_WITHOUT_V2: &str = r#"
+[42161]
+weth = "0x82aF49447D8a07e3bd95BD0d56f35241523fBab1"
+link = "0xf97f4df75117a78c1A5a0DBb814Af92458539FB4"
+uniswap_factory = "0x1F98431c8aD98523631AE4a59f267346ea31F984"
+uniswap_swap_router = "0x68b3465833fb72A70ecDF485E0e4C7bD8665Fc45"
+uniswap_quoter = "0x61fFE014bA17989E743c5F6cB21bF9697530B21e"
+ws_url = "ws://localhost:8546"
+http_url = "http://localhost:8545"
+"#;
+
+#[test]
+fn test_contracts_toml_with_optional_contracts_left_out() -> anyhow::Result<()> {
+    let contracts = ContractAddressMap::from_toml(ARBITRUM_WITHOUT_V2)?;
+    let arbitrum = contracts.get_address_for(Chain::Arbitrum)?;
+
+    assert!(!arbitrum.has_uniswap_v2());
+    assert!(arbitrum.uniswap_position_manager.is_none());
+    assert!(contracts.get_address_for(Chain::Mainnet).is_err());
+
+    // quote tokens default to WETH
+    assert_eq!(arbitrum.quote_tokens.len(), 1);
+    assert_eq!(arbitrum.quote_tokens[0].address, arbitrum.weth);
+
+    Ok(())
+}
+
+#[test]
+fn test_contracts_toml_rejects_unknown_and_duplicate_chains() {
+    let unknown_chain = ARBITRUM_WITHOUT_V2.replace("[42161]", "[not-a-chain]");
+    assert!(ContractAddressMap::from_toml(&unknown_chain).is_err());
+
+    let duplicate_chain = format!(
+        "{}{}",
+        ARBITRUM_WITHOUT_V2,
+        ARBITRUM_WITHOUT_V2.replace("[42161]", "[arbitrum]")
+    );
+    assert!(ContractAddressMap::from_toml(&duplicate_chain).is_err());
+}
+
+#[test]
+fn test_contracts_toml_reports_every_problem() {
+    let broken = ARBITRUM_WITHOUT_V2
+        .replace(
+            "weth = \"0x82aF49447D8a07e3bd95BD0d56f35241523fBab1\"",
+            "weth = \"0x82aF4944\"",
+        )
+        .replace(
+            "ws_url = \"ws://localhost:8546\"",
+            "ws_url = \"http://localhost:8546\"",
+        )
+        .replace("uniswap_quoter", "uniswap_qouter");
+
+    let error = ContractAddressMap::from_toml(&broken)
+        .err()
+        .expect("broken config parsed")
+        .to_string();
+
+    assert!(error.starts_with("4 problem(s)"), "{}", error);
+    assert!(error.contains("[42161] weth \"0x82aF4944\" is not an address"));
+    assert!(error.contains("[42161] uniswap_quoter is missing"));
+    assert!(error.contains("[42161] ws_url has scheme http, expected ws or wss"));
+    assert!(error.contains("[42161] unknown key uniswap_qouter"));
+}
+
+#[test]
+fn test_contracts_reload_rejects_runtime_changes() -> anyhow::Result<()> {
+    let running = ContractAddressMap::from_toml(ARBITRUM_WITHOUT_V2)?;
+
+    // formatting only, nothing loaded changes
+    let reformatted = format!("# arbitrum\n{}", ARBITRUM_WITHOUT_V2);
+    assert!(check_contracts_reload(&running, &reformatted).is_ok());
+
+    let new_rpc = ARBITRUM_WITHOUT_V2.replace("ws://localhost:8546", "ws://localhost:9546");
+    let error = check_contracts_reload(&running, &new_rpc)
+        .expect_err("rpc url changed at runtime")
+        .to_string();
+    assert_eq!(
+        error,
+        "contracts can't change at runtime, restart to apply: [arbitrum] ws_url"
+    );
+
+    Ok(())
+}
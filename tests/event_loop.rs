@@ -0,0 +1,63 @@
+use ethers::types::{Address, ValueOrArray};
+use snipper::data::lifecycle::TokenState;
+use snipper::data::token_data::TokenRegistry;
+use snipper::data::tokens::{Dex, Erc20Token};
+use snipper::event_loop::{backoff_delay, WatchedPools, INITIAL_BACKOFF, MAX_BACKOFF};
+use snipper::{uniswap_v2_events, uniswap_v3_events};
+use std::time::Duration;
+
+#[test]
+fn test_backoff_doubles_and_is_capped() {
+    assert_eq!(backoff_delay(0), INITIAL_BACKOFF);
+    assert_eq!(backoff_delay(1), Duration::from_secs(2));
+    assert_eq!(backoff_delay(3), Duration::from_secs(8));
+    assert_eq!(backoff_delay(10), MAX_BACKOFF);
+    assert_eq!(backoff_delay(u32::MAX), MAX_BACKOFF);
+}
+
+#[tokio::test]
+async fn test_only_pools_awaiting_liquidity_are_watched() {
+    let registry = TokenRegistry::new();
+    let awaiting = Erc20Token {
+        address: Address::random(),
+        pool_address: Address::random(),
+        state: TokenState::AwaitingLiquidity,
+        ..Default::default()
+    };
+    let pair = Erc20Token {
+        address: Address::random(),
+        pool_address: Address::random(),
+        dex: Dex::UniswapV2,
+        ..Default::default()
+    };
+    let tradable = Erc20Token {
+        address: Address::random(),
+        pool_address: Address::random(),
+        state: TokenState::Validating,
+        ..Default::default()
+    };
+    for token in [&awaiting, &pair, &tradable] {
+        registry.update_token(token).await;
+    }
+
+    let pools = WatchedPools::of(&registry).await;
+    assert_eq!(pools.v3, vec![awaiting.pool_address]);
+    assert_eq!(pools.v2, vec![pair.pool_address]);
+
+    // a pool that got its liquidity is no longer watched
+    registry
+        .mark_token_tradable_by_pool_address(awaiting.pool_address)
+        .await;
+    assert!(WatchedPools::of(&registry).await.v3.is_empty());
+}
+
+#[test]
+fn test_mint_filters_only_match_the_given_pools() {
+    let pools = vec![Address::random(), Address::random()];
+
+    let filter = uniswap_v3_events::set_mint_filter(pools.clone());
+    assert_eq!(filter.address, Some(ValueOrArray::Array(pools.clone())));
+
+    let filter = uniswap_v2_events::set_mint_filter(pools.clone());
+    assert_eq!(filter.address, Some(ValueOrArray::Array(pools)));
+}
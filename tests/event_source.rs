@@ -0,0 +1,39 @@
+use ethers::types::{Address, Block, Log, TxHash, H256, U64};
+use snipper::event_loop::Event;
+use snipper::event_source::{EventSource, ReplayEventSource, SessionRecorder};
+
+#[tokio::test]
+async fn test_recorded_session_replays_in_order() -> anyhow::Result<()> {
+    let path = std::env::temp_dir().join(format!("snipper_session_{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let block = Block::<TxHash> {
+        number: Some(U64::from(100)),
+        hash: Some(H256::from_low_u64_be(100)),
+        ..Default::default()
+    };
+    let log = Log {
+        address: Address::from_low_u64_be(1),
+        block_number: Some(U64::from(100)),
+        removed: Some(true),
+        ..Default::default()
+    };
+
+    let mut recorder = SessionRecorder::create(&path)?;
+    recorder.record(&Event::Log(log.clone()))?;
+    recorder.record(&Event::Block(block.clone()))?;
+
+    let mut replay = ReplayEventSource::open(&path).await?;
+    match replay.next_event().await? {
+        Some(Event::Log(replayed)) => assert_eq!(replayed, log),
+        _ => panic!("expected the recorded log first"),
+    }
+    match replay.next_event().await? {
+        Some(Event::Block(replayed)) => assert_eq!(replayed, block),
+        _ => panic!("expected the recorded block second"),
+    }
+    assert!(replay.next_event().await?.is_none());
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use ethers::types::U256;
+use snipper::swap::honeypot::RoundTrip;
+use snipper::swap::transfer_tax::TokenTaxes;
+
+fn round_trip(spent: u64, returned: u64) -> RoundTrip {
+    RoundTrip {
+        spent: U256::from(spent),
+        returned: U256::from(returned),
+        tokens_sold: U256::from(1_000),
+        tokens_left: U256::zero(),
+        sell_error: None,
+        taxes: TokenTaxes::default(),
+    }
+}
+
+#[test]
+fn test_round_trip_loss() {
+    assert_eq!(round_trip(1_000, 900).loss_bps(), 1_000);
+    assert_eq!(round_trip(1_000, 1_000).loss_bps(), 0);
+    // buying into a pool that moves up can return more than was spent
+    assert_eq!(round_trip(1_000, 1_200).loss_bps(), 0);
+    assert_eq!(round_trip(1_000, 0).loss_bps(), 10_000);
+}
+
+#[test]
+fn test_round_trip_within_loss_is_not_rejected() {
+    assert_eq!(round_trip(1_000, 900).rejection_reason(1_500), None);
+
+    let taxed = RoundTrip {
+        taxes: TokenTaxes {
+            buy_bps: 2_500,
+            sell_bps: 2_500,
+            transfer_bps: 0,
+        },
+        ..round_trip(1_000, 500)
+    };
+    assert_eq!(
+        taxed.rejection_reason(1_500).unwrap(),
+        "round trip lost 5000 bps, more than 1500 bps (taxes buy 2500 sell 2500 transfer 0)"
+    );
+}
+
+#[test]
+fn test_unsellable_tokens_are_rejected() {
+    let reverted = RoundTrip {
+        tokens_left: U256::from(1_000),
+        ..round_trip(1_000, 0)
+    };
+    assert_eq!(
+        reverted.rejection_reason(1_500).unwrap(),
+        "honeypot, test sell reverted"
+    );
+
+    // max transaction amounts only let part of the position out
+    let partial = RoundTrip {
+        tokens_left: U256::from(400),
+        ..round_trip(1_000, 990)
+    };
+    assert_eq!(
+        partial.rejection_reason(1_500).unwrap(),
+        "honeypot, test sell left 400 of 1000 tokens"
+    );
+
+    let failed = RoundTrip {
+        sell_error: Some("approve reverted".to_string()),
+        ..round_trip(1_000, 0)
+    };
+    assert!(failed
+        .rejection_reason(1_500)
+        .unwrap()
+        .ends_with("=> approve reverted"));
+}
@@ -0,0 +1,120 @@
+use ethers::abi::{encode, Token};
+use ethers::providers::Provider;
+use ethers::types::{Address, Bytes, U256};
+use snipper::abi::uniswap_pool::UNISWAP_V3_POOL;
+use snipper::data::tokens::Erc20Token;
+use snipper::multicall::Multicall;
+use snipper::swap::launch_price::{
+    fully_diluted_valuation, read_launch_price, sqrt_price_x96_to_token_price, LaunchPriceBounds,
+};
+use std::sync::Arc;
+
+fn q96() -> U256 {
+    U256::from(2).pow(U256::from(96))
+}
+
+#[test]
+fn test_sqrt_price_to_token_price() -> anyhow::Result<()> {
+    // sqrtPriceX96 of 2^96 is a raw price of 1
+    let price = sqrt_price_x96_to_token_price(q96(), true, 18, 18)?;
+    assert!((price - 1.0).abs() < 1e-12);
+
+    // sqrtPriceX96 of 2^97 means 4 token1 per token0
+    let doubled = q96() * 2;
+    let price_as_token_0 = sqrt_price_x96_to_token_price(doubled, true, 18, 18)?;
+    let price_as_token_1 = sqrt_price_x96_to_token_price(doubled, false, 18, 18)?;
+    assert!((price_as_token_0 - 4.0).abs() < 1e-12);
+    assert!((price_as_token_1 - 0.25).abs() < 1e-12);
+
+    // an 18 decimal token at a raw price of 1 against a 6 decimal stablecoin
+    let price_in_usdc = sqrt_price_x96_to_token_price(q96(), true, 18, 6)?;
+    assert!((price_in_usdc - 1e12).abs() < 1.0);
+
+    // extreme launch prices do not overflow
+    let extreme = U256::from(2).pow(U256::from(159));
+    assert!(sqrt_price_x96_to_token_price(extreme, true, 18, 18)?.is_finite());
+
+    Ok(())
+}
+
+#[test]
+fn test_bounds_reject_out_of_range_launches() -> anyhow::Result<()> {
+    let bounds = LaunchPriceBounds {
+        min_price_weth: Some(1e-12),
+        max_price_weth: Some(1.0),
+        min_fdv_weth: None,
+        max_fdv_weth: Some(10_000.0),
+    };
+
+    assert_eq!(bounds.rejection_reason(1e-6, 1_000.0), None);
+    assert!(bounds.rejection_reason(1e-15, 1_000.0).is_some());
+    assert!(bounds.rejection_reason(2.0, 1_000.0).is_some());
+    assert!(bounds.rejection_reason(1e-6, 1e9).is_some());
+    assert!(bounds.rejection_reason(f64::INFINITY, 1_000.0).is_some());
+
+    // one billion tokens at 1e-6 WETH is 1000 WETH
+    let supply = U256::from(1_000_000_000u64) * U256::exp10(18);
+    let fdv = fully_diluted_valuation(1e-6, supply, 18)?;
+    assert!((fdv - 1_000.0).abs() < 1e-6);
+
+    Ok(())
+}
+
+#[test]
+fn test_uninitialized_pool_is_not_rejected() -> anyhow::Result<()> {
+    let bounds = LaunchPriceBounds {
+        max_price_weth: Some(1.0),
+        ..Default::default()
+    };
+    let token = Erc20Token {
+        decimals: 18,
+        quote_decimals: 18,
+        is_token_0: true,
+        ..Default::default()
+    };
+    assert_eq!(bounds.token_rejection_reason(&token, 1.0)?, None);
+
+    // 4 WETH is above the bound, 4 DAI at 0.0005 WETH per DAI is not
+    let token = Erc20Token {
+        launch_sqrt_price_x96: q96() * 2,
+        ..token
+    };
+    assert!(bounds.token_rejection_reason(&token, 1.0)?.is_some());
+    assert_eq!(bounds.token_rejection_reason(&token, 0.0005)?, None);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_pool_without_code_is_not_initialized() {
+    let (provider, mock) = Provider::mocked();
+    let client = Arc::new(provider);
+    let pool = UNISWAP_V3_POOL::new(Address::random(), client.clone());
+
+    let mut multicall = Multicall::new(&client);
+    let pending = multicall.add(pool.slot_0());
+    let reverted = multicall.add(pool.slot_0());
+    let initialized = multicall.add(pool.slot_0());
+
+    let slot_0 = encode(&[
+        Token::Uint(q96()),
+        Token::Int(U256::from(200)),
+        Token::Uint(U256::zero()),
+        Token::Uint(U256::one()),
+        Token::Uint(U256::one()),
+        Token::Uint(U256::zero()),
+        Token::Bool(true),
+    ]);
+    // a pending createPool has no code at the pool address yet, calls to it return nothing
+    let results = encode(&[Token::Array(vec![
+        Token::Tuple(vec![Token::Bool(true), Token::Bytes(vec![])]),
+        Token::Tuple(vec![Token::Bool(false), Token::Bytes(vec![])]),
+        Token::Tuple(vec![Token::Bool(true), Token::Bytes(slot_0)]),
+    ])]);
+    mock.push::<Bytes, _>(Bytes::from(results)).unwrap();
+    let results = multicall.call().await.unwrap();
+
+    assert_eq!(read_launch_price(&results, &pending), (U256::zero(), 0));
+    assert_eq!(read_launch_price(&results, &reverted), (U256::zero(), 0));
+    assert_eq!(read_launch_price(&results, &initialized), (q96(), 200));
+}
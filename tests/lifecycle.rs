@@ -0,0 +1,182 @@
+use ethers::types::{Address, U256};
+use snipper::data::lifecycle::TokenState;
+use snipper::data::token_data::TokenRegistry;
+use snipper::data::tokens::Erc20Token;
+
+fn bought() -> TokenState {
+    TokenState::Bought {
+        amount: U256::from(500u64),
+        bought_at: 1_700_000_000,
+    }
+}
+
+#[test]
+fn test_full_lifecycle_is_recorded_in_order() {
+    let mut token = Erc20Token::default();
+
+    let path = [
+        TokenState::AwaitingLiquidity,
+        TokenState::Validating,
+        bought(),
+        TokenState::Selling {
+            amount: U256::from(500u64),
+            attempts: 1,
+        },
+        TokenState::Selling {
+            amount: U256::from(500u64),
+            attempts: 2,
+        },
+        TokenState::Sold,
+    ];
+    for state in path.iter() {
+        token.transition(state.clone()).unwrap();
+    }
+
+    assert_eq!(token.state, TokenState::Sold);
+    let states: Vec<TokenState> = token
+        .history
+        .iter()
+        .map(|change| change.state.clone())
+        .collect();
+    assert_eq!(states, path);
+    assert!(token
+        .history
+        .windows(2)
+        .all(|pair| pair[0].at <= pair[1].at));
+    assert!(token.history.iter().all(|change| change.at > 0));
+}
+
+#[test]
+fn test_impossible_transitions_are_refused() {
+    let mut token = Erc20Token {
+        name: "Skipper".to_string(),
+        ..Default::default()
+    };
+
+    // can't buy before liquidity is seen, can't sell before buying
+    token
+        .transition(bought())
+        .expect_err("bought straight from discovered");
+    token
+        .transition(TokenState::Sold)
+        .expect_err("sold straight from discovered");
+    assert_eq!(token.state, TokenState::Discovered);
+    assert!(token.history.is_empty());
+
+    token
+        .transition(TokenState::Rejected {
+            reason: "launch price too high".to_string(),
+        })
+        .unwrap();
+
+    // rejected is final
+    let error = token
+        .transition(TokenState::Validating)
+        .expect_err("validating after rejected");
+    assert!(error
+        .to_string()
+        .contains("rejected (launch price too high)"));
+    assert!(token.state.is_final());
+}
+
+#[test]
+fn test_stuck_keeps_the_amount_held() {
+    let mut token = Erc20Token {
+        state: bought(),
+        ..Default::default()
+    };
+
+    let amount = U256::from(500u64);
+    token
+        .transition(TokenState::Selling {
+            amount,
+            attempts: 1,
+        })
+        .unwrap();
+    token
+        .transition(TokenState::Stuck {
+            amount,
+            reason: "transfer reverted".to_string(),
+        })
+        .unwrap();
+
+    assert_eq!(token.state.amount_held(), amount);
+    assert!(token.state.is_final());
+    token
+        .transition(TokenState::Sold)
+        .expect_err("sold after stuck");
+}
+
+#[tokio::test]
+async fn test_registry_transitions_are_queryable() {
+    let token = Erc20Token {
+        name: "Queried".to_string(),
+        address: Address::random(),
+        pool_address: Address::random(),
+        ..Default::default()
+    };
+    let registry = TokenRegistry::new();
+
+    registry.update_token(&token).await;
+    assert_eq!(registry.get_number_of_tokens().await, 1);
+    assert!(!registry.is_token_tradable(token.address).await);
+
+    let marked = registry
+        .mark_token_tradable_by_pool_address(token.pool_address)
+        .await;
+    assert_eq!(marked.unwrap().state, TokenState::Validating);
+    // a second Mint changes nothing
+    assert!(registry
+        .mark_token_tradable_by_pool_address(token.pool_address)
+        .await
+        .is_none());
+    assert!(registry.is_token_tradable(token.address).await);
+
+    registry
+        .transition_token(token.address, bought())
+        .await
+        .unwrap();
+    let validating = registry
+        .get_tokens_in_state(|state| *state == TokenState::Validating)
+        .await;
+    assert!(validating.is_empty());
+    let positions = registry
+        .get_tokens_in_state(|state| matches!(state, TokenState::Bought { .. }))
+        .await;
+    assert_eq!(positions.len(), 1);
+
+    registry
+        .transition_token(token.address, TokenState::Validating)
+        .await
+        .expect_err("validating after bought");
+    registry
+        .transition_token(Address::random(), TokenState::Validating)
+        .await
+        .expect_err("untracked token");
+
+    registry
+        .transition_token(
+            token.address,
+            TokenState::Selling {
+                amount: U256::from(500u64),
+                attempts: 1,
+            },
+        )
+        .await
+        .unwrap();
+    registry
+        .transition_token(token.address, TokenState::Sold)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        registry.get_token_state(token.address).await,
+        Some(TokenState::Sold)
+    );
+    let history = registry.get_token_history(token.address).await.unwrap();
+    let names: Vec<&str> = history.iter().map(|change| change.state.name()).collect();
+    assert_eq!(names, ["validating", "bought", "selling", "sold"]);
+
+    // sold tokens stay queryable but are no longer counted
+    assert_eq!(registry.get_number_of_tokens().await, 0);
+}
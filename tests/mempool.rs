@@ -0,0 +1,180 @@
+use ethers::abi::AbiEncode;
+use ethers::types::{Address, Transaction, U256};
+use snipper::abi::uniswap_position_manager::{
+    CreateAndInitializePoolIfNecessaryCall, MintCall, MintParams, MulticallCall,
+};
+use snipper::abi::uniswap_router_v2::AddLiquidityETHCall;
+use snipper::abi::uniswap_v3_factory::CreatePoolCall;
+use snipper::mempool::{
+    compute_v2_pair_address, compute_v3_pool_address, decode_pending_launches, LaunchContracts,
+    PendingLaunch,
+};
+
+const WETH: &str = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2";
+const USDC: &str = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48";
+
+fn mainnet_contracts() -> LaunchContracts {
+    LaunchContracts {
+        uniswap_factory: "0x1F98431c8aD98523631AE4a59f267346ea31F984"
+            .parse()
+            .unwrap(),
+        uniswap_position_manager: Some(
+            "0xC36442b4a4522E871399CD717aBDD847Ab11FE88"
+                .parse()
+                .unwrap(),
+        ),
+        uniswap_v2_factory: Some(
+            "0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f"
+                .parse()
+                .unwrap(),
+        ),
+        uniswap_v2_router: Some(
+            "0x7a250d5630B4cF539739dF2C5dAcb4c659F2488D"
+                .parse()
+                .unwrap(),
+        ),
+        weth: WETH.parse().unwrap(),
+    }
+}
+
+fn pending_tx(to: Address, input: Vec<u8>) -> Transaction {
+    Transaction {
+        to: Some(to),
+        input: input.into(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_computes_known_pool_addresses() -> anyhow::Result<()> {
+    let contracts = mainnet_contracts();
+    let usdc: Address = USDC.parse()?;
+    let weth: Address = WETH.parse()?;
+
+    let v3_pool = compute_v3_pool_address(contracts.uniswap_factory, usdc, weth, 500);
+    let expected_v3_pool: Address = "0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640".parse()?;
+    assert_eq!(v3_pool, expected_v3_pool);
+
+    let v2_pair = compute_v2_pair_address(contracts.uniswap_v2_factory.unwrap(), usdc, weth);
+    let expected_v2_pair: Address = "0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc".parse()?;
+    assert_eq!(v2_pair, expected_v2_pair);
+
+    Ok(())
+}
+
+#[test]
+fn test_decodes_create_pool() -> anyhow::Result<()> {
+    let contracts = mainnet_contracts();
+    let call = CreatePoolCall {
+        token_a: contracts.weth,
+        token_b: USDC.parse()?,
+        fee: 500,
+    };
+
+    let launches = decode_pending_launches(
+        &pending_tx(contracts.uniswap_factory, call.encode()),
+        &contracts,
+    );
+
+    match launches.as_slice() {
+        [PendingLaunch::V3Pool(event)] => {
+            assert_eq!(event.token0, USDC.parse()?);
+            assert_eq!(event.token1, contracts.weth);
+            assert_eq!(event.tick_spacing, 10);
+            assert_eq!(
+                event.pool,
+                "0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640".parse()?
+            );
+        }
+        other => panic!("expected one v3 launch, got {:?}", other),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_decodes_position_manager_multicall() -> anyhow::Result<()> {
+    let contracts = mainnet_contracts();
+    let token: Address = Address::random();
+    let (token0, token1) = if token < contracts.weth {
+        (token, contracts.weth)
+    } else {
+        (contracts.weth, token)
+    };
+
+    let create = CreateAndInitializePoolIfNecessaryCall {
+        token_0: token0,
+        token_1: token1,
+        fee: 10000,
+        sqrt_price_x96: U256::from(1u8) << 96,
+    };
+    let mint = MintCall {
+        params: MintParams {
+            token_0: token0,
+            token_1: token1,
+            fee: 10000,
+            ..Default::default()
+        },
+    };
+    let multicall = MulticallCall {
+        data: vec![create.encode().into(), mint.encode().into()],
+    };
+
+    let launches = decode_pending_launches(
+        &pending_tx(
+            contracts.uniswap_position_manager.unwrap(),
+            multicall.encode(),
+        ),
+        &contracts,
+    );
+
+    // create and mint target the same pool, so it is reported once
+    match launches.as_slice() {
+        [PendingLaunch::V3Pool(event)] => {
+            assert_eq!(event.fee, 10000);
+            assert_eq!(event.tick_spacing, 200);
+            assert_eq!(
+                event.pool,
+                compute_v3_pool_address(contracts.uniswap_factory, token0, token1, 10000)
+            );
+        }
+        other => panic!("expected one v3 launch, got {:?}", other),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_decodes_add_liquidity_eth() -> anyhow::Result<()> {
+    let contracts = mainnet_contracts();
+    let call = AddLiquidityETHCall {
+        token: USDC.parse()?,
+        ..Default::default()
+    };
+
+    let launches = decode_pending_launches(
+        &pending_tx(contracts.uniswap_v2_router.unwrap(), call.encode()),
+        &contracts,
+    );
+
+    match launches.as_slice() {
+        [PendingLaunch::V2Pair(event)] => assert_eq!(
+            event.pair,
+            "0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc".parse()?
+        ),
+        other => panic!("expected one v2 launch, got {:?}", other),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_ignores_unrelated_transactions() {
+    let contracts = mainnet_contracts();
+
+    let to_other_contract = pending_tx(Address::random(), vec![1, 2, 3, 4]);
+    assert!(decode_pending_launches(&to_other_contract, &contracts).is_empty());
+
+    let garbage_to_router = pending_tx(contracts.uniswap_v2_router.unwrap(), vec![1, 2, 3, 4]);
+    assert!(decode_pending_launches(&garbage_to_router, &contracts).is_empty());
+}
@@ -0,0 +1,96 @@
+use ethers::abi::{encode, Token};
+use ethers::providers::{MockProvider, Provider};
+use ethers::types::{Address, Bytes, U256};
+use snipper::abi::erc20::ERC20;
+use snipper::multicall::{revert_reason, Multicall, MAX_CALLS_PER_BATCH};
+use std::sync::Arc;
+
+/// aggregate3 return data for (success, return data) pairs
+fn aggregate3_response(results: Vec<(bool, Vec<u8>)>) -> Bytes {
+    let results = results
+        .into_iter()
+        .map(|(success, data)| Token::Tuple(vec![Token::Bool(success), Token::Bytes(data)]))
+        .collect();
+
+    encode(&[Token::Array(results)]).into()
+}
+
+fn revert_data(reason: &str) -> Vec<u8> {
+    let mut data = vec![0x08, 0xc3, 0x79, 0xa0];
+    data.extend(encode(&[Token::String(reason.to_string())]));
+    data
+}
+
+fn mocked_client() -> (Arc<Provider<MockProvider>>, MockProvider) {
+    let (provider, mock) = Provider::mocked();
+    (Arc::new(provider), mock)
+}
+
+#[tokio::test]
+async fn test_each_call_reports_its_own_result() {
+    let (client, mock) = mocked_client();
+    let token = ERC20::new(Address::random(), client.clone());
+
+    let mut multicall = Multicall::new(&client);
+    let symbol = multicall.add(token.symbol());
+    let decimals = multicall.add(token.decimals());
+    let name = multicall.add(token.name());
+    let total_supply = multicall.add(token.total_supply());
+    assert_eq!(multicall.len(), 4);
+
+    mock.push::<Bytes, _>(aggregate3_response(vec![
+        (true, encode(&[Token::String("PEPE".to_string())])),
+        (true, encode(&[Token::Uint(U256::from(18))])),
+        (false, revert_data("name is not readable")),
+        // what a call to an address without code returns
+        (true, vec![]),
+    ]))
+    .unwrap();
+    let results = multicall.call().await.unwrap();
+
+    assert_eq!(results.get(&symbol).unwrap(), "PEPE");
+    assert_eq!(results.get(&decimals).unwrap(), 18);
+
+    let error = results.get(&name).expect_err("name reverted");
+    assert!(error.to_string().contains("name() on"));
+    assert!(error.to_string().contains("reverted: name is not readable"));
+
+    let error = results.get(&total_supply).expect_err("no return data");
+    assert!(error.to_string().contains("undecodable"));
+}
+
+#[tokio::test]
+async fn test_large_batches_are_split() {
+    let (client, mock) = mocked_client();
+    let token = ERC20::new(Address::random(), client.clone());
+
+    let mut multicall = Multicall::new(&client);
+    let handles: Vec<_> = (0..MAX_CALLS_PER_BATCH + 1)
+        .map(|_| multicall.add(token.decimals()))
+        .collect();
+
+    // the mock answers requests last pushed first
+    mock.push::<Bytes, _>(aggregate3_response(vec![(
+        true,
+        encode(&[Token::Uint(U256::from(6))]),
+    )]))
+    .unwrap();
+    let first_batch = vec![(true, encode(&[Token::Uint(U256::from(18))])); MAX_CALLS_PER_BATCH];
+    mock.push::<Bytes, _>(aggregate3_response(first_batch))
+        .unwrap();
+
+    let results = multicall.call().await.unwrap();
+    assert_eq!(results.get(&handles[0]).unwrap(), 18);
+    assert_eq!(results.get(&handles[MAX_CALLS_PER_BATCH]).unwrap(), 6);
+}
+
+#[test]
+fn test_revert_reason() {
+    assert_eq!(
+        revert_reason(&revert_data("TRANSFER_FAILED").into()),
+        Some("TRANSFER_FAILED".to_string())
+    );
+    assert_eq!(revert_reason(&Bytes::new()), None);
+    // custom errors have their own selector
+    assert_eq!(revert_reason(&vec![0xde, 0xad, 0xbe, 0xef].into()), None);
+}
@@ -0,0 +1,106 @@
+use ethers::types::H256;
+use snipper::reorg::{BlockCheck, BlockWindow};
+
+fn hash(n: u64) -> H256 {
+    H256::from_low_u64_be(n)
+}
+
+#[test]
+fn test_extends_and_ignores_duplicates() {
+    let mut window = BlockWindow::new(8);
+
+    assert_eq!(window.push(100, hash(100), hash(99)), BlockCheck::Extended);
+    assert_eq!(window.push(101, hash(101), hash(100)), BlockCheck::Extended);
+    assert_eq!(
+        window.push(101, hash(101), hash(100)),
+        BlockCheck::Duplicate
+    );
+    assert_eq!(window.len(), 2);
+}
+
+#[test]
+fn test_same_height_replacement_is_reorg() {
+    let mut window = BlockWindow::new(8);
+    window.push(100, hash(100), hash(99));
+    window.push(101, hash(101), hash(100));
+    window.push(102, hash(102), hash(101));
+
+    // sibling of 101 arrives, 101 and 102 are gone
+    match window.push(101, hash(1101), hash(100)) {
+        BlockCheck::Reorg(reorg) => {
+            assert_eq!(reorg.common_ancestor, 100);
+            assert_eq!(
+                reorg.removed_blocks,
+                vec![(101, hash(101)), (102, hash(102))]
+            );
+            assert_eq!(reorg.depth(), 2);
+        }
+        other => panic!("expected reorg, got {:?}", other),
+    }
+    assert_eq!(window.hash_at(101), Some(hash(1101)));
+    assert_eq!(window.hash_at(102), None);
+}
+
+#[test]
+fn test_parent_mismatch_asks_for_parent() {
+    let mut window = BlockWindow::new(8);
+    window.push(100, hash(100), hash(99));
+    window.push(101, hash(101), hash(100));
+
+    // new head 102 builds on a 101 we have not seen
+    assert_eq!(
+        window.push(102, hash(1102), hash(1101)),
+        BlockCheck::UnknownParent {
+            number: 101,
+            hash: hash(1101)
+        }
+    );
+
+    // once the new parent is pushed the head extends the new chain
+    assert!(matches!(
+        window.push(101, hash(1101), hash(100)),
+        BlockCheck::Reorg(_)
+    ));
+    assert_eq!(
+        window.push(102, hash(1102), hash(1101)),
+        BlockCheck::Extended
+    );
+}
+
+#[test]
+fn test_window_is_bounded() {
+    let mut window = BlockWindow::new(4);
+    for n in 0..10 {
+        window.push(n, hash(n), if n == 0 { H256::zero() } else { hash(n - 1) });
+    }
+
+    assert_eq!(window.len(), 4);
+    assert_eq!(window.hash_at(5), None);
+    assert_eq!(window.hash_at(6), Some(hash(6)));
+}
+
+#[test]
+fn test_reprocessed_blocks_of_the_new_chain_are_duplicates() {
+    let mut window = BlockWindow::new(8);
+    window.push(100, hash(100), hash(99));
+    window.push(101, hash(101), hash(100));
+    window.push(102, hash(102), hash(101));
+
+    // the new chain 101' 102' 103' arrives as its head, parents fetched first
+    assert!(matches!(
+        window.push(101, hash(1101), hash(100)),
+        BlockCheck::Reorg(_)
+    ));
+    window.push(102, hash(1102), hash(1101));
+    window.push(103, hash(1103), hash(1102));
+
+    // the event loop runs 101' and 102' again after the reorg, that must not be another one
+    assert_eq!(
+        window.push(101, hash(1101), hash(100)),
+        BlockCheck::Duplicate
+    );
+    assert_eq!(
+        window.push(102, hash(1102), hash(1101)),
+        BlockCheck::Duplicate
+    );
+}
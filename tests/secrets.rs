@@ -0,0 +1,90 @@
+use ethers::core::rand::thread_rng;
+use ethers::signers::{LocalWallet, Signer};
+use ethers::types::Chain;
+use snipper::data::contracts::ContractAddressMap;
+use snipper::utils::secrets::{interpolate_env, load_keystore_wallet, InterpolationError};
+
+#[test]
+fn test_interpolate_env() {
+    std::env::set_var("SNIPPER_TEST_RPC_KEY", "abc123");
+    std::env::remove_var("SNIPPER_TEST_UNSET_A");
+    std::env::remove_var("SNIPPER_TEST_UNSET_B");
+
+    assert_eq!(
+        interpolate_env("wss://rpc.example.com/v2/${SNIPPER_TEST_RPC_KEY}"),
+        Ok("wss://rpc.example.com/v2/abc123".to_string())
+    );
+    assert_eq!(
+        interpolate_env("no variables"),
+        Ok("no variables".to_string())
+    );
+
+    assert_eq!(
+        interpolate_env("${SNIPPER_TEST_UNSET_A}/${SNIPPER_TEST_UNSET_B}"),
+        Err(InterpolationError::UnsetVars(vec![
+            "SNIPPER_TEST_UNSET_A".to_string(),
+            "SNIPPER_TEST_UNSET_B".to_string(),
+        ]))
+    );
+    assert_eq!(
+        interpolate_env("https://${SNIPPER_TEST_RPC_KEY"),
+        Err(InterpolationError::Unterminated)
+    );
+    assert_eq!(interpolate_env("${}"), Err(InterpolationError::EmptyName));
+}
+
+#[test]
+fn test_chain_with_unset_variables_is_unavailable() -> anyhow::Result<()> {
+    std::env::remove_var("SNIPPER_TEST_BASE_KEY");
+    let config = r#"
+[base]
+weth = "0x4200000000000000000000000000000000000006"
+link = "0x88Fb150BDc53A65fe94Dea0c9BA0a6dAf8C6e196"
+uniswap_factory = "0x33128a8fC17869897dcE68Ed026d694621f6FDfD"
+uniswap_swap_router = "0x2626664c2603336E57B271c5C0b26F421741e481"
+uniswap_quoter = "0x3d4e44Eb1374240CE5F1B871ab261CD16335B76a"
+ws_url = "wss://base.example.com/v2/${SNIPPER_TEST_BASE_KEY}"
+http_url = "https://base.example.com/v2/${SNIPPER_TEST_BASE_KEY}"
+
+[mainnet]
+weth = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"
+link = "0x514910771AF9Ca656af840dff83E8264EcF986CA"
+uniswap_factory = "0x1F98431c8aD98523631AE4a59f267346ea31F984"
+uniswap_swap_router = "0x68b3465833fb72A70ecDF485E0e4C7bD8665Fc45"
+uniswap_quoter = "0x61fFE014bA17989E743c5F6cB21bF9697530B21e"
+ws_url = "ws://localhost:8546"
+http_url = "http://localhost:8545"
+"#;
+
+    let contracts = ContractAddressMap::from_toml(config)?;
+
+    assert!(contracts.get_address_for(Chain::Mainnet).is_ok());
+    let error = contracts
+        .get_address_for(Chain::Base)
+        .err()
+        .expect("base loaded without its api key")
+        .to_string();
+    assert!(error.contains("SNIPPER_TEST_BASE_KEY"), "{}", error);
+
+    Ok(())
+}
+
+#[test]
+fn test_load_keystore_wallet() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("snipper-keystore-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+
+    let (wallet, _) =
+        LocalWallet::new_keystore(&dir, &mut thread_rng(), "hunter2", Some("signer.json"))?;
+    let path = dir.join("signer.json");
+    let path = path.to_str().unwrap();
+
+    let loaded = load_keystore_wallet(path, "hunter2", Chain::Base)?;
+    assert_eq!(loaded.address(), wallet.address());
+    assert_eq!(loaded.chain_id(), 8453);
+
+    assert!(load_keystore_wallet(path, "wrong password", Chain::Base).is_err());
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
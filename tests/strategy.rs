@@ -0,0 +1,168 @@
+use ethers::types::U256;
+use ethers::utils::parse_ether;
+use snipper::strategy::{SharedStrategy, Strategy};
+use snipper::swap::launch_price::LaunchPriceBounds;
+
+const STRATEGY: &str = r#"
+default_profile = "default"
+
+[profiles.default]
+buy_amount_eth = "0.1"
+hold_time_secs = 60
+
+[profiles.cautious]
+buy_amount_eth = "0.02"
+slippage_bps = 100
+"#;
+
+#[test]
+fn test_strategy_profiles() -> anyhow::Result<()> {
+    let default = Strategy::from_toml(STRATEGY, None)?;
+    assert_eq!(default.profile, "default");
+    assert_eq!(default.buy_amount, parse_ether("0.1")?);
+    assert_eq!(default.hold_time_secs, 60);
+
+    let cautious = Strategy::from_toml(STRATEGY, Some("cautious"))?;
+    assert_eq!(cautious.buy_amount, parse_ether("0.02")?);
+    assert_eq!(cautious.slippage_bps, 100);
+    // left out fields keep the built-in values
+    assert_eq!(cautious.sell_gas_limit, 1_000_000);
+    assert_eq!(cautious.wrap_amount, parse_ether("10")?);
+
+    assert_eq!(cautious.honeypot_test_amount, parse_ether("0.001")?);
+    assert_eq!(cautious.max_round_trip_loss_bps, 1_500);
+
+    assert!(Strategy::from_toml(STRATEGY, Some("yolo")).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_min_amount_out_applies_slippage() -> anyhow::Result<()> {
+    let strategy = Strategy::from_toml(STRATEGY, None)?;
+
+    assert_eq!(strategy.min_amount_out(U256::from(1_000)), U256::from(980));
+
+    Ok(())
+}
+
+#[test]
+fn test_strategy_reports_every_problem() {
+    let broken = r#"
+[profiles.default]
+buy_amount_eth = "lots"
+slippage_bps = 10000
+
+[profiles.greedy]
+buy_amount_eth = "20"
+sell_gas_limit = 1000
+hold_time_secs = 4294967295
+"#;
+
+    let error = Strategy::from_toml(broken, None)
+        .expect_err("broken strategy parsed")
+        .to_string();
+
+    assert!(error.starts_with("5 problem(s)"), "{}", error);
+    assert!(error.contains("[default] buy_amount_eth \"lots\" is not an ETH amount"));
+    assert!(error.contains("[default] slippage_bps 10000 has to be below 10000"));
+    assert!(error.contains("[greedy] buy_amount_eth 20 is more than wrap_amount_eth 10.0"));
+    assert!(error.contains("[greedy] sell_gas_limit 1000 is below 21000"));
+    assert!(error.contains("[greedy] hold_time_secs 4294967295 is more than 2592000"));
+}
+
+#[test]
+fn test_reload_applies_changes_and_logs_a_diff() -> anyhow::Result<()> {
+    let shared = SharedStrategy::new(Strategy::from_toml(STRATEGY, None)?);
+
+    let edited = STRATEGY
+        .replace("buy_amount_eth = \"0.1\"", "buy_amount_eth = \"0.2\"")
+        .replace(
+            "hold_time_secs = 60",
+            "hold_time_secs = 60\nblacklist = [\"0x4200000000000000000000000000000000000006\"]",
+        );
+    let diff = shared.reload(Strategy::from_toml(&edited, Some("default"))?)?;
+
+    assert_eq!(
+        diff,
+        vec![
+            "buy_amount: 0.100000000000000000 ETH -> 0.200000000000000000 ETH".to_string(),
+            "blacklist: none -> 0x4200000000000000000000000000000000000006".to_string(),
+        ]
+    );
+    let current = shared.current();
+    assert_eq!(current.buy_amount, parse_ether("0.2")?);
+    assert!(current.is_blacklisted("0x4200000000000000000000000000000000000006".parse()?));
+
+    Ok(())
+}
+
+#[test]
+fn test_reload_rejects_fields_fixed_at_startup() -> anyhow::Result<()> {
+    let shared = SharedStrategy::new(Strategy::from_toml(STRATEGY, None)?);
+
+    let edited = STRATEGY.replace(
+        "hold_time_secs = 60",
+        "hold_time_secs = 30\nwrap_amount_eth = \"20\"",
+    );
+    let error = shared
+        .reload(Strategy::from_toml(&edited, Some("default"))?)
+        .expect_err("wrap amount changed at runtime");
+    assert_eq!(
+        error.to_string(),
+        "wrap_amount can't change at runtime, restart to apply"
+    );
+
+    // nothing of a rejected reload is applied
+    assert_eq!(shared.current().hold_time_secs, 60);
+
+    let other_profile = Strategy::from_toml(STRATEGY, Some("cautious"))?;
+    assert!(shared.reload(other_profile).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_launch_price_bounds_are_checked_and_reloaded() -> anyhow::Result<()> {
+    let shared = SharedStrategy::new(Strategy::from_toml(STRATEGY, None)?);
+    assert_eq!(
+        shared.current().launch_price_bounds,
+        LaunchPriceBounds::default()
+    );
+
+    let edited = STRATEGY.replace(
+        "hold_time_secs = 60",
+        "hold_time_secs = 60\nmax_launch_price_weth = 1.0\nmin_launch_fdv_weth = 5",
+    );
+    let diff = shared.reload(Strategy::from_toml(&edited, Some("default"))?)?;
+    assert_eq!(
+        diff,
+        vec![
+            "launch_price: any -> up to 1 WETH".to_string(),
+            "launch_fdv: any -> 5 WETH or more".to_string(),
+        ]
+    );
+    assert_eq!(
+        shared.current().launch_price_bounds,
+        LaunchPriceBounds {
+            max_price_weth: Some(1.0),
+            min_fdv_weth: Some(5.0),
+            ..Default::default()
+        }
+    );
+
+    let broken = STRATEGY.replace(
+        "hold_time_secs = 60",
+        "hold_time_secs = 60\nmin_launch_price_weth = 2.0\nmax_launch_price_weth = 1.0\nmax_launch_fdv_weth = -1",
+    );
+    let error = Strategy::from_toml(&broken, None)
+        .expect_err("inverted launch bounds parsed")
+        .to_string();
+    assert!(error.starts_with("2 problem(s)"), "{}", error);
+    assert!(
+        error.contains("[default] min_launch_price_weth 2 is more than max_launch_price_weth 1")
+    );
+    assert!(error.contains("[default] max_launch_fdv_weth -1 has to be 0 or more"));
+
+    Ok(())
+}
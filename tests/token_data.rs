@@ -0,0 +1,91 @@
+use ethers::types::{Address, Chain, U256};
+use snipper::data::contracts::{current_chain, with_chain};
+use snipper::data::lifecycle::TokenState;
+use snipper::data::token_data::TokenRegistry;
+use snipper::data::tokens::Erc20Token;
+
+#[tokio::test]
+async fn test_registries_exist_side_by_side() {
+    let token = Erc20Token {
+        name: "Mainnet Token".to_string(),
+        address: Address::random(),
+        ..Default::default()
+    };
+    let mainnet = TokenRegistry::new();
+    let base = TokenRegistry::new();
+
+    with_chain(Chain::Mainnet, async {
+        assert_eq!(current_chain().unwrap(), Chain::Mainnet);
+        mainnet.update_token(&token).await;
+        assert!(mainnet.get_token(token.address).await.is_some());
+    })
+    .await;
+
+    with_chain(Chain::Base, async {
+        assert_eq!(current_chain().unwrap(), Chain::Base);
+        assert!(base.get_token(token.address).await.is_none());
+        assert_eq!(base.get_number_of_tokens().await, 0);
+    })
+    .await;
+
+    assert_eq!(mainnet.get_number_of_tokens().await, 1);
+    // outside a scope nothing falls back to a default chain
+    assert!(current_chain().is_err());
+}
+
+#[tokio::test]
+async fn test_clones_share_their_tokens() {
+    let registry = TokenRegistry::new();
+    let handle = registry.clone();
+    let token = Erc20Token {
+        name: "Shared".to_string(),
+        address: Address::random(),
+        pool_address: Address::random(),
+        ..Default::default()
+    };
+
+    handle.update_token(&token).await;
+    let tokens = registry.get_tokens().await;
+    assert_eq!(tokens[&token.address].name, "Shared");
+
+    let removed = registry
+        .remove_token_by_pool_address(token.pool_address)
+        .await;
+    assert_eq!(removed.unwrap().address, token.address);
+    assert!(handle.get_token(token.address).await.is_none());
+}
+
+#[tokio::test]
+async fn test_reorged_out_pool_keeps_an_open_position() {
+    let registry = TokenRegistry::new();
+    let token = Erc20Token {
+        name: "Held".to_string(),
+        address: Address::random(),
+        pool_address: Address::random(),
+        state: TokenState::Bought {
+            amount: U256::from(1_000),
+            bought_at: 1,
+        },
+        ..Default::default()
+    };
+    registry.update_token(&token).await;
+
+    let kept = registry
+        .remove_token_by_pool_address(token.pool_address)
+        .await
+        .unwrap();
+    assert!(matches!(
+        kept.state,
+        TokenState::Stuck { amount, .. } if amount == U256::from(1_000)
+    ));
+    assert_eq!(
+        registry.get_token_state(token.address).await,
+        Some(kept.state)
+    );
+
+    // a second reorg of the same pool leaves the stuck position alone
+    registry
+        .remove_token_by_pool_address(token.pool_address)
+        .await;
+    assert!(registry.get_token(token.address).await.is_some());
+}
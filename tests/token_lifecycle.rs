@@ -3,18 +3,19 @@ use ethers::providers::{Middleware, Provider, Ws};
 use ethers::types::{Address, BlockNumber, U256};
 use snipper::abi::uniswap_pool::UNISWAP_V3_POOL;
 use snipper::abi::uniswap_v3_factory::UNISWAP_V3_FACTORY;
-use snipper::data::contracts::CONTRACT;
-use snipper::data::token_data::{
-    check_all_tokens_and_update_if_are_tradable, get_and_save_erc20_by_token_address,
-    get_number_of_tokens, is_token_tradable,
+use snipper::data::contracts::{select_chain, CONTRACT};
+use snipper::data::lifecycle::TokenState;
+use snipper::data::token_data::TokenRegistry;
+use snipper::data::tokens::{
+    buy_eligible_tokens_on_anvil, purchase_token_on_anvil, sell_eligible_tokens_on_anvil,
 };
-use snipper::data::tokens::{buy_eligible_tokens_on_anvil, sell_eligible_tokens_on_anvil};
+use snipper::strategy::{load_strategy, SharedStrategy, Strategy};
 use snipper::swap::anvil_simlator::AnvilSimulator;
 use snipper::uniswap_v3_events::PoolCreatedEvent;
-use std::str::FromStr;
 use std::sync::Arc;
 
 struct TestSetup {
+    registry: TokenRegistry,
     // client: Arc<Provider<Ws>>,
     anvil_simulator: Arc<AnvilSimulator>,
     token_address: Address,
@@ -24,9 +25,17 @@ struct TestSetup {
 }
 
 async fn setup(token_address: Address) -> anyhow::Result<TestSetup> {
+    setup_with_strategy(token_address, load_strategy(None)?).await
+}
+
+async fn setup_with_strategy(
+    token_address: Address,
+    strategy: Strategy,
+) -> anyhow::Result<TestSetup> {
     dotenv().ok();
+    select_chain("mainnet")?;
 
-    let ws_url = CONTRACT.get_address().ws_url.clone();
+    let ws_url = CONTRACT.get_address().ws_url.to_string();
     let provider = Provider::<Ws>::connect(ws_url.clone()).await?;
     let client = Arc::new(provider);
 
@@ -34,12 +43,10 @@ async fn setup(token_address: Address) -> anyhow::Result<TestSetup> {
     let last_block_timestamp = initial_block.timestamp.as_u32();
     println!("initial block timestamp => {}", last_block_timestamp);
 
-    let sell_after_str =
-        std::env::var("SELL_TOKEN_AFTER").expect("SELL_TOKEN_AFTER not found in .env");
-    let sell_after = u32::from_str(&sell_after_str)?;
+    let sell_after = strategy.hold_time_secs;
 
-    let factory_address: Address = CONTRACT.get_address().uniswap_factory.parse()?;
-    let weth_address: Address = CONTRACT.get_address().weth.parse()?;
+    let factory_address: Address = CONTRACT.get_address().uniswap_factory;
+    let weth_address: Address = CONTRACT.get_address().weth;
     let factory = UNISWAP_V3_FACTORY::new(factory_address, client.clone());
 
     let mut pool_address = factory
@@ -69,18 +76,30 @@ async fn setup(token_address: Address) -> anyhow::Result<TestSetup> {
         pool: pool_address,
     };
 
-    get_and_save_erc20_by_token_address(&pool_created_event, &client).await?;
+    // every test gets its own registry, so they can run side by side
+    let registry = TokenRegistry::new();
+    registry
+        .get_and_save_erc20_by_token_address(
+            &pool_created_event,
+            &strategy.launch_price_bounds,
+            &client,
+        )
+        .await?;
 
     // Create an instance of AnvilSimulator
-    let anvil_simulator = AnvilSimulator::new(&ws_url).await?;
+    let anvil_simulator = AnvilSimulator::new(&ws_url, SharedStrategy::new(strategy)).await?;
     let anvil_simulator = Arc::new(anvil_simulator);
 
     // check token liquidity
-    if let Err(error) = check_all_tokens_and_update_if_are_tradable(&client).await {
+    if let Err(error) = registry
+        .check_all_tokens_and_update_if_are_tradable(&client)
+        .await
+    {
         println!("could not check token tradability => {}", error);
     }
 
     Ok(TestSetup {
+        registry,
         anvil_simulator,
         token_address,
         last_block_timestamp,
@@ -93,14 +112,18 @@ async fn test_anvil_ai_token_buy_sell_test() -> anyhow::Result<()> {
     let token_address: Address = "0x821b37dc08e534207d8beae9b42a60443fd067b2".parse()?;
     let mut setup = setup(token_address).await?;
 
-    let mut number_of_tokens = get_number_of_tokens().await;
+    let mut number_of_tokens = setup.registry.get_number_of_tokens().await;
     assert_eq!(number_of_tokens, 1);
 
-    let token_tradable = is_token_tradable(setup.token_address).await;
+    let token_tradable = setup.registry.is_token_tradable(setup.token_address).await;
     assert!(token_tradable);
 
-    if let Err(error) =
-        buy_eligible_tokens_on_anvil(&setup.anvil_simulator, setup.last_block_timestamp).await
+    if let Err(error) = buy_eligible_tokens_on_anvil(
+        &setup.registry,
+        &setup.anvil_simulator,
+        setup.last_block_timestamp,
+    )
+    .await
     {
         println!("error running buy_eligible_tokens_on_anvil => {}", error);
     }
@@ -113,13 +136,17 @@ async fn test_anvil_ai_token_buy_sell_test() -> anyhow::Result<()> {
 
     setup.last_block_timestamp += setup.sell_after;
 
-    if let Err(error) =
-        sell_eligible_tokens_on_anvil(&setup.anvil_simulator, setup.last_block_timestamp).await
+    if let Err(error) = sell_eligible_tokens_on_anvil(
+        &setup.registry,
+        &setup.anvil_simulator,
+        setup.last_block_timestamp,
+    )
+    .await
     {
         println!("error running sell_eligible_tokens_on_anvil => {}", error);
     }
 
-    number_of_tokens = get_number_of_tokens().await;
+    number_of_tokens = setup.registry.get_number_of_tokens().await;
 
     token_balance = setup
         .anvil_simulator
@@ -134,17 +161,22 @@ async fn test_anvil_ai_token_buy_sell_test() -> anyhow::Result<()> {
 #[tokio::test]
 #[ignore]
 async fn test_anvil_token_buy_sell_test() -> anyhow::Result<()> {
-    let token_address: Address = CONTRACT.get_address().link.parse()?;
+    select_chain("mainnet")?;
+    let token_address: Address = CONTRACT.get_address().link;
     let mut setup = setup(token_address).await?;
 
-    let mut number_of_tokens = get_number_of_tokens().await;
+    let mut number_of_tokens = setup.registry.get_number_of_tokens().await;
     assert_eq!(number_of_tokens, 1);
 
-    let token_tradable = is_token_tradable(setup.token_address).await;
+    let token_tradable = setup.registry.is_token_tradable(setup.token_address).await;
     assert!(token_tradable);
 
-    if let Err(error) =
-        buy_eligible_tokens_on_anvil(&setup.anvil_simulator, setup.last_block_timestamp).await
+    if let Err(error) = buy_eligible_tokens_on_anvil(
+        &setup.registry,
+        &setup.anvil_simulator,
+        setup.last_block_timestamp,
+    )
+    .await
     {
         println!("error running buy_eligible_tokens_on_anvil => {}", error);
     }
@@ -157,13 +189,17 @@ async fn test_anvil_token_buy_sell_test() -> anyhow::Result<()> {
 
     setup.last_block_timestamp += setup.sell_after;
 
-    if let Err(error) =
-        sell_eligible_tokens_on_anvil(&setup.anvil_simulator, setup.last_block_timestamp).await
+    if let Err(error) = sell_eligible_tokens_on_anvil(
+        &setup.registry,
+        &setup.anvil_simulator,
+        setup.last_block_timestamp,
+    )
+    .await
     {
         println!("error running sell_eligible_tokens_on_anvil => {}", error);
     }
 
-    number_of_tokens = get_number_of_tokens().await;
+    number_of_tokens = setup.registry.get_number_of_tokens().await;
 
     token_balance = setup
         .anvil_simulator
@@ -178,17 +214,22 @@ async fn test_anvil_token_buy_sell_test() -> anyhow::Result<()> {
 #[tokio::test]
 #[ignore]
 async fn test_anvil_token_buy_no_sell_test() -> anyhow::Result<()> {
-    let token_address: Address = CONTRACT.get_address().link.parse()?;
+    select_chain("mainnet")?;
+    let token_address: Address = CONTRACT.get_address().link;
     let mut setup = setup(token_address).await?;
 
-    let mut number_of_tokens = get_number_of_tokens().await;
+    let mut number_of_tokens = setup.registry.get_number_of_tokens().await;
     assert_eq!(number_of_tokens, 1);
 
-    let token_tradable = is_token_tradable(setup.token_address).await;
+    let token_tradable = setup.registry.is_token_tradable(setup.token_address).await;
     assert!(token_tradable);
 
-    if let Err(error) =
-        buy_eligible_tokens_on_anvil(&setup.anvil_simulator, setup.last_block_timestamp).await
+    if let Err(error) = buy_eligible_tokens_on_anvil(
+        &setup.registry,
+        &setup.anvil_simulator,
+        setup.last_block_timestamp,
+    )
+    .await
     {
         println!("error running buy_eligible_tokens_on_anvil => {}", error);
     }
@@ -202,13 +243,17 @@ async fn test_anvil_token_buy_no_sell_test() -> anyhow::Result<()> {
     // Increase time by less than sell_after
     setup.last_block_timestamp += setup.sell_after - 10;
 
-    if let Err(error) =
-        sell_eligible_tokens_on_anvil(&setup.anvil_simulator, setup.last_block_timestamp).await
+    if let Err(error) = sell_eligible_tokens_on_anvil(
+        &setup.registry,
+        &setup.anvil_simulator,
+        setup.last_block_timestamp,
+    )
+    .await
     {
         println!("error running sell_eligible_tokens_on_anvil => {}", error);
     }
 
-    number_of_tokens = get_number_of_tokens().await;
+    number_of_tokens = setup.registry.get_number_of_tokens().await;
     let new_token_balance = setup
         .anvil_simulator
         .get_token_balance_by_address(setup.token_address)
@@ -219,3 +264,36 @@ async fn test_anvil_token_buy_no_sell_test() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+#[ignore]
+async fn test_anvil_blacklisted_token_with_liquidity_is_not_bought() -> anyhow::Result<()> {
+    select_chain("mainnet")?;
+    let token_address: Address = CONTRACT.get_address().link;
+    let mut strategy = load_strategy(None)?;
+    strategy.blacklist.push(token_address);
+    let setup = setup_with_strategy(token_address, strategy).await?;
+
+    // the immediate buy path at launch goes straight to purchase_token_on_anvil
+    let token = setup.registry.get_token(token_address).await.unwrap();
+    assert_eq!(token.state, TokenState::Validating);
+    purchase_token_on_anvil(
+        &setup.registry,
+        &token,
+        &setup.anvil_simulator,
+        setup.last_block_timestamp,
+    )
+    .await?;
+
+    let token_balance = setup
+        .anvil_simulator
+        .get_token_balance_by_address(token_address)
+        .await?;
+    assert_eq!(token_balance, U256::from(0));
+    assert_eq!(
+        setup.registry.get_token_state(token_address).await,
+        Some(TokenState::Validating)
+    );
+
+    Ok(())
+}
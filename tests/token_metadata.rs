@@ -0,0 +1,102 @@
+use ethers::abi::{encode, Token};
+use ethers::providers::Provider;
+use ethers::types::{Address, Bytes, U256};
+use snipper::data::token_metadata::{
+    decode_string, sanitize_metadata, MetadataCalls, DEFAULT_DECIMALS, MAX_METADATA_CHARS,
+};
+use snipper::multicall::Multicall;
+use std::sync::Arc;
+
+/// aggregate3 return data for (success, return data) pairs
+fn aggregate3_response(results: Vec<(bool, Vec<u8>)>) -> Bytes {
+    let results = results
+        .into_iter()
+        .map(|(success, data)| Token::Tuple(vec![Token::Bool(success), Token::Bytes(data)]))
+        .collect();
+
+    encode(&[Token::Array(results)]).into()
+}
+
+fn bytes32(text: &str) -> Vec<u8> {
+    let mut data = text.as_bytes().to_vec();
+    data.resize(32, 0);
+    data
+}
+
+#[tokio::test]
+async fn test_non_standard_token_is_read_with_fallbacks() {
+    let (provider, mock) = Provider::mocked();
+    let client = Arc::new(provider);
+    let token_address = Address::random();
+
+    let mut multicall = Multicall::new(&client);
+    let calls = MetadataCalls::add(&mut multicall, token_address, &client);
+
+    // name, symbol, decimals, totalSupply
+    mock.push::<Bytes, _>(aggregate3_response(vec![
+        (false, vec![]),
+        (true, bytes32("MKR")),
+        (true, vec![]),
+        (true, encode(&[Token::Uint(U256::from(1_000_000))])),
+    ]))
+    .unwrap();
+    let metadata = calls.read(&multicall.call().await.unwrap());
+
+    assert_eq!(metadata.symbol, "MKR");
+    assert_eq!(metadata.name, "MKR");
+    assert_eq!(metadata.decimals, DEFAULT_DECIMALS);
+    assert_eq!(metadata.total_supply, U256::from(1_000_000));
+    assert_eq!(metadata.missing, vec!["name", "decimals"]);
+}
+
+#[tokio::test]
+async fn test_token_without_metadata_is_named_after_its_address() {
+    let (provider, mock) = Provider::mocked();
+    let client = Arc::new(provider);
+    let token_address = Address::random();
+
+    let mut multicall = Multicall::new(&client);
+    let calls = MetadataCalls::add(&mut multicall, token_address, &client);
+
+    // decimals above 255 don't fit a u8
+    mock.push::<Bytes, _>(aggregate3_response(vec![
+        (true, vec![]),
+        (false, vec![]),
+        (true, encode(&[Token::Uint(U256::from(256))])),
+        (false, vec![]),
+    ]))
+    .unwrap();
+    let metadata = calls.read(&multicall.call().await.unwrap());
+
+    assert_eq!(metadata.name, format!("{:?}", token_address));
+    assert_eq!(metadata.symbol, metadata.name);
+    assert_eq!(metadata.total_supply, U256::zero());
+    assert_eq!(
+        metadata.missing,
+        vec!["name", "symbol", "decimals", "total_supply"]
+    );
+}
+
+#[test]
+fn test_decode_string() {
+    let encoded = encode(&[Token::String("Pepe".to_string())]);
+    assert_eq!(decode_string(&encoded.into()), Some("Pepe".to_string()));
+    assert_eq!(
+        decode_string(&bytes32("DAI").into()),
+        Some("DAI".to_string())
+    );
+    assert_eq!(decode_string(&bytes32("").into()), None);
+    assert_eq!(decode_string(&Bytes::new()), None);
+}
+
+#[test]
+fn test_sanitize_metadata() {
+    assert_eq!(sanitize_metadata("PEPE\n\u{1b}[31mRUG"), "PEPE[31mRUG");
+    // right-to-left override and cyrillic lookalikes
+    assert_eq!(sanitize_metadata("\u{202e}USDС"), "?USD?");
+    assert_eq!(sanitize_metadata("  Token  "), "Token");
+    assert_eq!(
+        sanitize_metadata(&"A".repeat(200)).len(),
+        MAX_METADATA_CHARS
+    );
+}
@@ -0,0 +1,95 @@
+use snipper::data::token_risk::{scan_bytecode, Capability, RISKY_SIGNATURES};
+use snipper::utils::type_conversion::get_function_selector;
+
+const PUSH4: u8 = 0x63;
+const PUSH32: u8 = 0x7f;
+const EQ: u8 = 0x14;
+const DELEGATECALL: u8 = 0xf4;
+
+/// a function dispatcher comparing the calldata selector against `signatures`
+fn dispatcher(signatures: &[&str]) -> Vec<u8> {
+    let mut code = vec![];
+    for signature in signatures {
+        code.push(PUSH4);
+        code.extend(get_function_selector(signature));
+        code.push(EQ);
+    }
+    code
+}
+
+#[test]
+fn test_plain_erc20_is_clean() {
+    let report = scan_bytecode(&dispatcher(&[
+        "transfer(address,uint256)",
+        "approve(address,uint256)",
+        "balanceOf(address)",
+    ]));
+
+    assert!(report.is_clean());
+    assert_eq!(report.selectors_found, 3);
+    assert!(report.signatures.is_empty());
+}
+
+#[test]
+fn test_risky_functions_are_reported_once() {
+    let report = scan_bytecode(&dispatcher(&[
+        "transfer(address,uint256)",
+        "setBuyTax(uint256)",
+        "setSellTax(uint256)",
+        "mint(address,uint256)",
+        "openTrading()",
+        "setBots(address[])",
+    ]));
+
+    assert_eq!(
+        report.capabilities,
+        vec![
+            Capability::Blacklist,
+            Capability::Mint,
+            Capability::SetFee,
+            Capability::TransferGating
+        ]
+    );
+    assert!(report.has(Capability::SetFee));
+    assert!(!report.has(Capability::Pause));
+    assert_eq!(report.signatures.len(), 5);
+    assert!(!report.is_clean());
+}
+
+#[test]
+fn test_push_data_and_metadata_are_not_read_as_code() {
+    // a risky selector hidden inside a PUSH32 constant
+    let mut code = dispatcher(&["transfer(address,uint256)"]);
+    code.push(PUSH32);
+    code.push(PUSH4);
+    code.extend(get_function_selector("pause()"));
+    code.extend([0u8; 27]);
+
+    // solc metadata, a CBOR map with a length suffix, containing a DELEGATECALL byte
+    let metadata = [0xa2, 0x64, DELEGATECALL, 0x00];
+    code.extend(metadata);
+    code.extend((metadata.len() as u16).to_be_bytes());
+
+    let report = scan_bytecode(&code);
+    assert!(report.is_clean(), "{:?}", report);
+    assert_eq!(report.selectors_found, 1);
+}
+
+#[test]
+fn test_delegatecall_is_flagged() {
+    let mut code = dispatcher(&["transfer(address,uint256)"]);
+    code.push(DELEGATECALL);
+
+    let report = scan_bytecode(&code);
+    assert!(report.delegates);
+    assert!(!report.is_clean());
+}
+
+#[test]
+fn test_catalogue_has_no_duplicate_signatures() {
+    let mut signatures: Vec<&str> = RISKY_SIGNATURES.iter().map(|(_, s)| *s).collect();
+    signatures.sort();
+    signatures.dedup();
+
+    assert_eq!(signatures.len(), RISKY_SIGNATURES.len());
+}
@@ -0,0 +1,201 @@
+use ethers::types::{Address, Chain, U256};
+use snipper::data::lifecycle::TokenState;
+use snipper::data::token_data::TokenRegistry;
+use snipper::data::token_store::{replay_journal, JournalEntry, TokenJournal};
+use snipper::data::tokens::{reconcile_position_balances, Dex, Erc20Token};
+use snipper::swap::transfer_tax::TokenTaxes;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+fn store_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("snipper-store-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+
+    dir
+}
+
+fn position(name: &str) -> Erc20Token {
+    Erc20Token {
+        name: name.to_string(),
+        address: Address::random(),
+        pool_address: Address::random(),
+        dex: Dex::UniswapV2,
+        state: TokenState::Bought {
+            amount: U256::from(1_000_000u64),
+            bought_at: 1_700_000_000,
+        },
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_journal_replays_upserts_and_removes() {
+    let dir = store_dir("replay");
+    let path = TokenJournal::path(&dir, Chain::Mainnet);
+    let kept = position("Kept");
+    let removed = position("Removed");
+
+    {
+        let (mut journal, tokens) = TokenJournal::open(&path).unwrap();
+        assert!(tokens.is_empty());
+
+        for entry in [
+            JournalEntry::Upsert {
+                token: Box::new(kept.clone()),
+            },
+            JournalEntry::Upsert {
+                token: Box::new(removed.clone()),
+            },
+            JournalEntry::Upsert {
+                token: Box::new(Erc20Token {
+                    state: TokenState::Sold,
+                    ..kept.clone()
+                }),
+            },
+            JournalEntry::Remove {
+                address: removed.address,
+            },
+        ] {
+            journal.append(&entry).unwrap();
+        }
+    }
+
+    let tokens = replay_journal(&path).unwrap();
+    assert_eq!(tokens.len(), 1);
+    let token = tokens.values().next().unwrap();
+    assert_eq!(token.address, kept.address);
+    assert_eq!(token.state, TokenState::Sold);
+    assert_eq!(token.dex, Dex::UniswapV2);
+
+    // reopening compacts the journal to one line per token
+    let (_, tokens) = TokenJournal::open(&path).unwrap();
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 1);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_torn_last_line_is_skipped() {
+    let dir = store_dir("torn");
+    let path = TokenJournal::path(&dir, Chain::Mainnet);
+    let token = position("Torn");
+
+    {
+        let (mut journal, _) = TokenJournal::open(&path).unwrap();
+        journal
+            .append(&JournalEntry::Upsert {
+                token: Box::new(token),
+            })
+            .unwrap();
+    }
+    let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+    file.write_all(br#"{"op":"upsert","token":{"name":"#)
+        .unwrap();
+
+    assert_eq!(replay_journal(&path).unwrap().len(), 1);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_bad_line_before_the_end_is_an_error() {
+    let dir = store_dir("corrupt");
+    let path = TokenJournal::path(&dir, Chain::Mainnet);
+    fs::create_dir_all(&dir).unwrap();
+
+    let upsert = serde_json::to_string(&JournalEntry::Upsert {
+        token: Box::new(position("Fine")),
+    })
+    .unwrap();
+    fs::write(&path, format!("not json\n{}\n", upsert)).unwrap();
+
+    let error = replay_journal(&path).expect_err("corrupt journal should not load");
+    assert!(error.to_string().contains("journal line 1"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_registry_changes_survive_a_restart() {
+    let dir = store_dir("registry");
+    let held = position("Held");
+    let sold = position("Sold");
+    let taxes = TokenTaxes {
+        buy_bps: 300,
+        sell_bps: 500,
+        transfer_bps: 100,
+    };
+
+    {
+        let (registry, stored) = TokenRegistry::open(&dir, Chain::Base).unwrap();
+        assert_eq!(stored, 0);
+
+        registry.update_token(&held).await;
+        registry.update_token(&sold).await;
+        registry.remove_token(sold.address).await;
+        registry.record_taxes(held.address, taxes).await.unwrap();
+    }
+
+    // a fresh registry stands in for the restarted process
+    let (registry, stored) = TokenRegistry::open(&dir, Chain::Base).unwrap();
+    assert_eq!(stored, 1);
+    assert_eq!(registry.get_number_of_tokens().await, 1);
+
+    let token = registry.get_token(held.address).await.unwrap();
+    assert_eq!(token.name, "Held");
+    assert_eq!(token.state, held.state);
+    assert_eq!(token.taxes, Some(taxes));
+    assert!(registry.get_token(sold.address).await.is_none());
+
+    // every chain has its own journal
+    let (_, stored) = TokenRegistry::open(&dir, Chain::Optimism).unwrap();
+    assert_eq!(stored, 0);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_positions_survive_a_restart_on_a_fresh_fork() {
+    let dir = store_dir("reconcile");
+    let lost = position("Lost");
+    let held = position("Held");
+
+    {
+        let (registry, _) = TokenRegistry::open(&dir, Chain::Base).unwrap();
+        registry.update_token(&lost).await;
+        registry.update_token(&held).await;
+    }
+
+    // the new fork holds nothing of `lost` and more of `held` than was recorded
+    let (registry, stored) = TokenRegistry::open(&dir, Chain::Base).unwrap();
+    assert_eq!(stored, 2);
+    let balances = HashMap::from([(held.address, U256::from(2_000_000u64))]);
+    reconcile_position_balances(&registry, &balances)
+        .await
+        .unwrap();
+
+    let token = registry.get_token(lost.address).await.unwrap();
+    match &token.state {
+        TokenState::Stuck { amount, reason } => {
+            assert_eq!(*amount, lost.state.amount_held());
+            assert!(reason.contains("after a restart"), "{}", reason);
+        }
+        state => panic!("lost position is {}", state),
+    }
+    let token = registry.get_token(held.address).await.unwrap();
+    assert_eq!(token.state.amount_held(), U256::from(2_000_000u64));
+
+    // the stuck position is still there after the next restart
+    drop(registry);
+    let (registry, stored) = TokenRegistry::open(&dir, Chain::Base).unwrap();
+    assert_eq!(stored, 2);
+    assert!(matches!(
+        registry.get_token(lost.address).await.unwrap().state,
+        TokenState::Stuck { .. }
+    ));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,24 @@
+use ethers::types::U256;
+use snipper::swap::transfer_tax::{after_tax, tax_bps, TokenTaxes, BASIS_POINTS};
+
+#[test]
+fn test_tax_bps() {
+    assert_eq!(tax_bps(U256::from(1_000), U256::from(950)), 500);
+    assert_eq!(tax_bps(U256::from(1_000), U256::from(1_000)), 0);
+    // more than quoted arrives when the pool moved in our favour
+    assert_eq!(tax_bps(U256::from(1_000), U256::from(1_100)), 0);
+    assert_eq!(tax_bps(U256::from(1_000), U256::zero()), BASIS_POINTS);
+    assert_eq!(tax_bps(U256::zero(), U256::zero()), 0);
+}
+
+#[test]
+fn test_after_tax() {
+    assert_eq!(after_tax(U256::from(1_000), 500), U256::from(950));
+    assert_eq!(after_tax(U256::from(1_000), 0), U256::from(1_000));
+    assert_eq!(
+        after_tax(U256::from(1_000), TokenTaxes::UNKNOWN.sell_bps),
+        U256::zero()
+    );
+    // measured taxes can't take more than everything
+    assert_eq!(after_tax(U256::from(1_000), 20_000), U256::zero());
+}
@@ -0,0 +1,67 @@
+use ethers::abi::{encode, Token};
+use ethers::contract::EthEvent;
+use ethers::types::{Address, Bytes, Log, H256, U256};
+use snipper::abi::uniswap_factory_v2::PairCreatedFilter;
+use snipper::abi::uniswap_pair::MintFilter;
+use snipper::abi::uniswap_pool::MintFilter as V3MintFilter;
+use snipper::uniswap_v2_events::{decode_paircreated_event, is_mint_log, is_pair_created_log};
+use snipper::uniswap_v3_events::EventDecodeError;
+
+#[test]
+fn test_decode_pair_created_event() -> anyhow::Result<()> {
+    let token0 = Address::random();
+    let token1 = Address::random();
+    let pair = Address::random();
+    let log = Log {
+        topics: vec![
+            PairCreatedFilter::signature(),
+            H256::from(token0),
+            H256::from(token1),
+        ],
+        data: Bytes::from(encode(&[
+            Token::Address(pair),
+            Token::Uint(U256::from(1u32)),
+        ])),
+        ..Default::default()
+    };
+
+    assert!(is_pair_created_log(&log));
+
+    let event = decode_paircreated_event(&log)?;
+    assert_eq!(event.token0, token0);
+    assert_eq!(event.token1, token1);
+    assert_eq!(event.pair, pair);
+
+    Ok(())
+}
+
+#[test]
+fn test_non_pair_created_log_is_error() {
+    let signature = H256::random();
+    let log = Log {
+        topics: vec![signature],
+        ..Default::default()
+    };
+
+    assert!(!is_pair_created_log(&log));
+    assert_eq!(
+        decode_paircreated_event(&log),
+        Err(EventDecodeError::UnknownSignature(signature))
+    );
+}
+
+#[test]
+fn test_is_mint_log_only_matches_v2_mints() {
+    let v2_mint = Log {
+        topics: vec![MintFilter::signature(), H256::from(Address::random())],
+        ..Default::default()
+    };
+    let v3_mint = Log {
+        topics: vec![V3MintFilter::signature()],
+        ..Default::default()
+    };
+
+    assert!(is_mint_log(&v2_mint));
+    assert!(!is_mint_log(&v3_mint));
+    assert!(!is_mint_log(&Log::default()));
+}
@@ -0,0 +1,170 @@
+use ethers::abi::{encode, Token};
+use ethers::contract::EthEvent;
+use ethers::types::{Address, Bytes, Log, H256, I256, U256};
+use snipper::abi::uniswap_pool::{MintFilter, SwapFilter};
+use snipper::abi::uniswap_v3_factory::PoolCreatedFilter;
+use snipper::uniswap_v3_events::{
+    decode_poolcreated_event, decode_uniswap_event, EventDecodeError, UniswapEvent,
+};
+
+fn address_topic(address: Address) -> H256 {
+    H256::from(address)
+}
+
+fn u256_topic(value: U256) -> H256 {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    H256::from(bytes)
+}
+
+fn pool_created_log(token0: Address, token1: Address, pool: Address) -> Log {
+    Log {
+        topics: vec![
+            PoolCreatedFilter::signature(),
+            address_topic(token0),
+            address_topic(token1),
+            u256_topic(U256::from(10000u32)),
+        ],
+        data: Bytes::from(encode(&[
+            Token::Int(U256::from(200u32)),
+            Token::Address(pool),
+        ])),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_decode_pool_created_event() -> anyhow::Result<()> {
+    let token0 = Address::random();
+    let token1 = Address::random();
+    let pool = Address::random();
+    let log = pool_created_log(token0, token1, pool);
+
+    let event = decode_poolcreated_event(&log)?;
+
+    assert_eq!(event.token0, token0);
+    assert_eq!(event.token1, token1);
+    assert_eq!(event.fee, 10000);
+    assert_eq!(event.tick_spacing, 200);
+    assert_eq!(event.pool, pool);
+
+    Ok(())
+}
+
+#[test]
+fn test_decode_mint_event() -> anyhow::Result<()> {
+    let pool = Address::random();
+    let owner = Address::random();
+    let log = Log {
+        address: pool,
+        topics: vec![
+            MintFilter::signature(),
+            address_topic(owner),
+            u256_topic(U256::MAX - 99), // tick -100, sign extended
+            u256_topic(U256::from(100u32)),
+        ],
+        data: Bytes::from(encode(&[
+            Token::Address(Address::random()),
+            Token::Uint(U256::from(5000u32)),
+            Token::Uint(U256::from(1u32)),
+            Token::Uint(U256::from(2u32)),
+        ])),
+        ..Default::default()
+    };
+
+    match decode_uniswap_event(&log)? {
+        UniswapEvent::Mint {
+            pool: mint_pool,
+            event,
+        } => {
+            assert_eq!(mint_pool, pool);
+            assert_eq!(event.owner, owner);
+            assert_eq!(event.tick_lower, -100);
+            assert_eq!(event.tick_upper, 100);
+            assert_eq!(event.amount, 5000);
+        }
+        other => panic!("expected Mint, got {:?}", other),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_decode_swap_event() -> anyhow::Result<()> {
+    let log = Log {
+        topics: vec![
+            SwapFilter::signature(),
+            address_topic(Address::random()),
+            address_topic(Address::random()),
+        ],
+        data: Bytes::from(encode(&[
+            Token::Int(I256::from(-5).into_raw()),
+            Token::Int(U256::from(7u32)),
+            Token::Uint(U256::from(1u32) << 96),
+            Token::Uint(U256::from(1000u32)),
+            Token::Int(U256::zero()),
+        ])),
+        ..Default::default()
+    };
+
+    let event = decode_uniswap_event(&log)?;
+    assert_eq!(event.name(), "Swap");
+
+    Ok(())
+}
+
+#[test]
+fn test_unknown_signature_is_error() {
+    let signature = H256::random();
+    let log = Log {
+        topics: vec![signature],
+        ..Default::default()
+    };
+
+    assert_eq!(
+        decode_uniswap_event(&log),
+        Err(EventDecodeError::UnknownSignature(signature))
+    );
+    assert_eq!(
+        decode_uniswap_event(&Log::default()),
+        Err(EventDecodeError::MissingSignature)
+    );
+}
+
+#[test]
+fn test_malformed_log_is_error() {
+    let mut log = pool_created_log(Address::random(), Address::random(), Address::random());
+    log.data = Bytes::from(vec![0u8; 7]);
+
+    match decode_uniswap_event(&log) {
+        Err(EventDecodeError::Malformed { event, .. }) => assert_eq!(event, "PoolCreated"),
+        other => panic!("expected malformed error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_decode_poolcreated_rejects_other_events() {
+    let log = Log {
+        topics: vec![
+            MintFilter::signature(),
+            address_topic(Address::random()),
+            u256_topic(U256::zero()),
+            u256_topic(U256::zero()),
+        ],
+        data: Bytes::from(encode(&[
+            Token::Address(Address::random()),
+            Token::Uint(U256::zero()),
+            Token::Uint(U256::zero()),
+            Token::Uint(U256::zero()),
+        ])),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        decode_poolcreated_event(&log),
+        Err(EventDecodeError::UnexpectedEvent {
+            expected: "PoolCreated",
+            found: "Mint",
+        })
+    );
+}